@@ -1,3 +1,3 @@
 from .path_finder import PathFinder
 from .map import Sc2Map
-from .mappings import MapType, MapsType
\ No newline at end of file
+from .mappings import FormationType, MapType, MapsType, TravelMode
\ No newline at end of file
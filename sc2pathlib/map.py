@@ -1,10 +1,10 @@
 # noinspection PyUnresolvedReferences
 from .sc2pathlib import VisionUnit
-from .sc2pathlib import Map
+from .sc2pathlib import Map, OverlordSpot, ThreatUnit, InfluenceShape, PathExposure, Island, DropSpot
 import numpy as np
 from typing import List, Optional, Tuple, Union
 from .choke import Choke
-from .mappings import MapsType, MapType, VisionStatus
+from .mappings import FormationType, MapsType, MapType, TravelMode, VisionStatus
 from sc2.unit import Unit
 from sc2.position import Point2
 
@@ -50,6 +50,43 @@ class Sc2Map:
         self._overlord_spots = self._map.overlord_spots
         return self._overlord_spots
 
+    @property
+    def overlord_spot_details(self) -> List[OverlordSpot]:
+        """
+        Overlord spots with plateau size, height, distance to ground and scouting metadata.
+        """
+        return self._map.overlord_spot_details
+
+    def calculate_overlord_spots(
+        self, expansions: List[Tuple[float, float]], sight_range: float = 11, anti_air_range: float = 7
+    ):
+        """
+        Calculates which expansions and chokes each overlord spot can see and how exposed it is to ground anti-air.
+        Use this on initialization after expansion locations are known.
+        """
+        self._map.calculate_overlord_spots(expansions, sight_range, anti_air_range)
+
+    def best_overlord_spots(
+        self, base: Tuple[float, float], occupied: List[Tuple[float, float]], count: int = 1
+    ) -> List[OverlordSpot]:
+        """
+        Finds the best overlord spots for scouting the base, skipping spots near the occupied positions.
+        """
+        return self._map.best_overlord_spots(base, occupied, count)
+
+    def islands(
+        self,
+        start_locations: List[Tuple[float, float]],
+        resources: Optional[List[Tuple[float, float]]] = None,
+        min_area: Optional[int] = None,
+    ) -> List[Island]:
+        """
+        Finds walkable areas that can't be reached by ground from the start locations,
+        such as islands, pockets and cliff-locked expansions.
+        Each island has its area, centroid, the given resources next to it and the closest drop-in points by air.
+        """
+        return self._map.islands(start_locations, resources, min_area)
+
     @property
     def chokes(self) -> List[Choke]:
         if self._chokes is not None:
@@ -61,6 +98,86 @@ class Sc2Map:
     def reset(self):
         self._map.reset()
 
+    def advance(self, game_loop: int):
+        """
+        Removes hazards and timed blocks that have expired by the game loop and decays influence layers.
+        """
+        self._map.advance(game_loop)
+
+    def add_hazard(
+        self,
+        shape: InfluenceShape,
+        influence: float,
+        expires_at_loop: int,
+        maps_type: MapsType = MapsType.Both,
+        fade: float = 0,
+    ):
+        """
+        Use this for short-lived effects, like Psionic Storm or Corrosive Bile.
+        The influence is kept through reset until advance reaches the expiry game loop.
+        """
+        self._map.add_hazard(shape, influence, expires_at_loop, maps_type, fade)
+
+    def add_timed_block(self, center: Tuple[float, float], size: Tuple[int, int], expires_at_loop: int):
+        """
+        Use this for Force Fields, the block is kept through reset until advance reaches the expiry game loop.
+        Ground and reaper pathing are blocked, colossi walk over the block.
+        On expiry cells covered by other blocks stay blocked, the rest get hazards and decay layers back
+        but lose other influence added since the last reset.
+        """
+        self._map.add_timed_block(center, size, expires_at_loop)
+
+    def add_decay_layer(self, name: str, half_life: float, maps_type: MapsType = MapsType.Ground):
+        """
+        Creates a persistent influence layer that halves every half_life game loops.
+        The layer is kept through reset and used by find_path and lowest_influence.
+        Raises ValueError when half_life isn't above 0.
+        """
+        self._map.add_decay_layer(name, half_life, maps_type)
+
+    def remove_decay_layer(self, name: str) -> bool:
+        return self._map.remove_decay_layer(name)
+
+    def observe_decay_layer(
+        self, name: str, points: List[Tuple[float, float]], influence: float, distance: float, blend: float = 0.5
+    ) -> bool:
+        """
+        Blends new observations into the layer. Blend of 1 replaces old values where something was observed.
+        Raises ValueError when distance isn't above 0.
+        """
+        return self._map.observe_decay_layer(name, points, influence, distance, blend)
+
+    def decay_influence(self, name: str, point: Tuple[float, float]) -> float:
+        return self._map.decay_influence(name, point)
+
+    def decay_layer(self, name: str) -> Optional[np.ndarray]:
+        values = self._map.decay_layer(name)
+        if values is None:
+            return None
+        return np.array(values)
+
+    def build_distance_field(self, name: str, sources: List[Tuple[float, float]], map_type: MapType = MapType.Ground):
+        """
+        Calculates walking distances from the closest source to every cell once, e.g. distances to our main base.
+        Build the field again when pathing changes.
+        """
+        self._map.build_distance_field(name, sources, map_type)
+
+    def remove_distance_field(self, name: str) -> bool:
+        return self._map.remove_distance_field(name)
+
+    def distance_from(self, name: str, point: Tuple[float, float]) -> Optional[float]:
+        """
+        Walking distance from the point to the closest source of the field, None if it can't be reached.
+        """
+        return self._map.distance_from(name, point)
+
+    def path_from(self, name: str, point: Tuple[float, float]) -> Optional[List[Tuple[int, int]]]:
+        """
+        Shortest path from the point to the closest source of the field, None if it can't be reached.
+        """
+        return self._map.path_from(name, point)
+
     def calculate_zones(self, sorted_base_locations: List[Tuple[float, float]]):
         """
         Use this on initialization to calculate zones.
@@ -76,6 +193,13 @@ class Sc2Map:
         """
         return self._map.get_zone(position)
 
+    def zone_distance(self, a: int, b: int, map_type: MapType = MapType.Ground) -> Optional[float]:
+        """
+        Walking distance between the centers of two zones, calculated along with the zones.
+        Distances are updated when blocks change the pathing, None if the zones aren't connected.
+        """
+        return self._map.zone_distance(a, b, map_type)
+
     def calculate_connections(self, start: Tuple[float, float]):
         """
         Calculates ground connections to a single point in the map.
@@ -128,10 +252,36 @@ class Sc2Map:
         self, points: List["Point2"], influence: float, tank_min_range: float = 2.5, tank_max_range: float = 14.5
     ):
         """
+        Adds sieged tanks as ground threats without fading, see add_threats.
         :param tank_min_range: Tank minimum range is 2, adding both unit radiuses to that and we'll estimate it to be 2.5.
         :param tank_max_range: Same for max range, 13, but but with unit radius, let's say it's 14.5 instead to err on the safe side
         """
-        self._map.add_influence_flat_hollow(points, influence, tank_min_range, tank_max_range)
+        threats = [ThreatUnit(point, tank_max_range, tank_min_range, influence, True, False, 0) for point in points]
+        self._map.add_threats(threats, 0)
+
+    def add_threats(self, threats: List[ThreatUnit], fade_margin: float = 2):
+        """
+        Adds dps of each threat as influence on the maps it can hit, using weapon range plus unit radius.
+        Influence fades out over fade_margin and area inside minimum range is left untouched.
+        """
+        self._map.add_threats(threats, fade_margin)
+
+    def add_unit_threats(self, units: List[Unit], fade_margin: float = 2):
+        """
+        Creates threats from python-sc2 units, ground and air weapons are handled separately.
+        Units with minimum range, like sieged tanks, should be added with add_threats.
+        """
+        threats: List[ThreatUnit] = []
+        for unit in units:
+            if unit.can_attack_ground:
+                threats.append(
+                    ThreatUnit(unit.position_tuple, unit.ground_range, 0, unit.ground_dps, True, False, unit.radius)
+                )
+            if unit.can_attack_air:
+                threats.append(
+                    ThreatUnit(unit.position_tuple, unit.air_range, 0, unit.air_dps, False, True, unit.radius)
+                )
+        self._map.add_threats(threats, fade_margin)
 
     def add_pure_ground_influence(
         self, points: List["Point2"], influence: float, full_range: float, fade_max_range: float
@@ -152,6 +302,13 @@ class Sc2Map:
     def add_both_influence(self, points: List["Point2"], influence: float, full_range: float, fade_max_range: float):
         self._map.add_influence_fading(MapsType.Both, points, influence, full_range, fade_max_range)
 
+    def add_shape_influence(self, maps_type: MapsType, shape: InfluenceShape, influence: float, fade: float = 0):
+        """
+        Use this for directional attacks, like Lurker spines, Disruptor shots or Liberator zones.
+        With fade above zero the influence fades out over that distance from the edge of the shape.
+        """
+        self._map.add_influence_shape(maps_type, shape, influence, fade)
+
     def current_influence(self, map_type: MapType, position: Tuple[float, float]):
         """
         Finds the current influence in the position
@@ -194,6 +351,159 @@ class Sc2Map:
     ) -> Tuple[List[Tuple[int, int]], float]:
         return self.find_path(map_type, start, end, large, influence=True)
 
+    def is_reachable(self, map_type: MapType, start: Tuple[float, float], end: Tuple[float, float]) -> bool:
+        """
+        Checks whether a path exists between the points without searching for it.
+        Blocked points are never reachable.
+        """
+        return self._map.is_reachable(map_type, start, end)
+
+    def find_path_constrained(
+        self,
+        map_type: MapType,
+        start: Tuple[float, float],
+        end: Tuple[float, float],
+        max_influence: Optional[int] = None,
+        exposure_budget: Optional[float] = None,
+    ) -> Optional[Tuple[List[Tuple[int, int]], float]]:
+        """
+        Finds the shortest path that never enters a cell with influence above max_influence
+        and keeps total exposure (influence multiplied by distance) under exposure_budget.
+        Influence is measured above the normal influence of the map.
+        The search is approximate and bounded, a path that barely fits the budget may be missed.
+        Returns None when no such path is found.
+        """
+        return self._map.find_path_constrained(map_type, start, end, max_influence, exposure_budget)
+
+    def find_paths_cooperative(
+        self,
+        map_type: MapType,
+        starts: List[Tuple[float, float]],
+        goals: List[Tuple[float, float]],
+        window: Optional[int] = None,
+        radii: Optional[List[float]] = None,
+    ) -> List[List[Tuple[int, int]]]:
+        """
+        Finds paths for a batch of units that don't run into each other, earlier units have priority.
+        Each path has one cell per time step and repeated cells mean the unit waits.
+        A unit blocked in by others stops as close to its goal as it can get.
+        window is how many steps units plan ahead around each other, radii are unit sizes.
+        Larger units only move where they fit and stop at the closest cell to the goal where they fit.
+        Raises ValueError when starts and goals have different lengths.
+        """
+        return self._map.find_paths_cooperative(map_type, starts, goals, window, radii)
+
+    def find_flee_path(
+        self, map_type: MapType, start: Tuple[float, float], threats: List[Tuple[float, float]], distance: float
+    ) -> Tuple[List[Tuple[int, int]], float]:
+        """
+        Finds a path within walking distance that gets away from the threats.
+        Routes through influence and destinations in corners or dead ends are penalized.
+        """
+        return self._map.find_flee_path(map_type, start, threats, distance)
+
+    def safe_direction(self, map_type: MapType, position: Tuple[float, float], radius: float) -> Tuple[float, float]:
+        """
+        Unit vector pointing towards decreasing influence, smoothed over pathable cells within radius.
+        Returns (0, 0) when influence is flat.
+        """
+        return self._map.safe_direction(map_type, position, radius)
+
+    def find_transport_path(
+        self,
+        start: Tuple[float, float],
+        goal: Tuple[float, float],
+        air_speed: float = 1,
+        pickup_cost: float = 2,
+        drop_cost: float = 2,
+    ) -> Optional[Tuple[List[Tuple[TravelMode, List[Tuple[int, int]], float]], float]]:
+        """
+        Finds the cheapest plan from start to goal that combines walking and flying in a transport.
+        air_speed is the speed of the transport relative to the walking unit and must be positive,
+        pickup and drop costs are the overhead of getting in and out in walking distance.
+        Returns the legs in order as (mode, path, cost) and the total cost, None if the goal can't be reached.
+        A start at the goal gives a single walking leg without movement.
+        """
+        result = self._map.find_transport_path(start, goal, air_speed, pickup_cost, drop_cost)
+        if result is None:
+            return None
+
+        legs, total = result
+        return [(TravelMode(leg.mode), leg.path, leg.cost) for leg in legs], total
+
+    def find_drop_spots(
+        self,
+        target: Tuple[float, float],
+        start: Tuple[float, float],
+        defenses: Optional[List[Tuple[float, float]]] = None,
+        count: int = 1,
+        radius: Optional[float] = None,
+    ) -> List[DropSpot]:
+        """
+        Finds landing spots for Medivac, Warp Prism or Overlord drops near the target, such as a mineral line.
+        The target can be a mineral field position, its height level is taken from the closest pathable cell.
+        Spots are on the height level of the target and ranked by air path exposure, estimated enemy vision
+        and distance to the defenses. Each spot has the recommended air path from start.
+        """
+        return self._map.find_drop_spots(target, start, defenses or [], count, radius)
+
+    def find_attack_positions(
+        self,
+        map_type: MapType,
+        target: Tuple[float, float],
+        weapon_range: float,
+        unit_radius: float,
+        count: int = 5,
+        start: Optional[Tuple[float, float]] = None,
+    ) -> List[Tuple[Tuple[int, int], float]]:
+        """
+        Finds cells from which the unit can hit the target, best first as (position, score).
+        Score is influence plus travel distance from start, high ground that sees the target gets a bonus.
+        Add target radius to weapon_range when needed.
+        """
+        return self._map.find_attack_positions(map_type, target, weapon_range, unit_radius, count, start)
+
+    def concave_positions(
+        self, units: List[Unit], target: Tuple[float, float], range: float
+    ) -> List[Optional[Tuple[float, float]]]:
+        """
+        Finds non-overlapping ground positions on an arc at range from the target, facing the units.
+        The arc spans at most a half circle and units that don't fit go to rows behind it.
+        Returned positions are in the same order as the units and minimize total travel.
+        Units that didn't fit in the arc or the rows behind it get None.
+        """
+        unit_data = [(unit.position_tuple, unit.radius) for unit in units]
+        return self._map.concave_positions(unit_data, target, range)
+
+    def formation_paths(
+        self,
+        map_type: MapType,
+        units: List[Unit],
+        path: List[Tuple[float, float]],
+        formation: FormationType = FormationType.Box,
+        spacing: float = 1.5,
+    ) -> List[List[Tuple[float, float]]]:
+        """
+        Offsets the path for each unit to keep a line, box or wedge formation.
+        The formation is compressed through chokes and expands again afterwards.
+        Paths are returned in the same order as the units.
+        """
+        return self._map.formation_paths(map_type, [unit.position_tuple for unit in units], path, formation, spacing)
+
+    def path_exposure(
+        self,
+        map_type: MapType,
+        path: List[Tuple[float, float]],
+        unit_speed: float,
+        threshold: Optional[int] = None,
+    ) -> PathExposure:
+        """
+        Integrates influence along a path of cells or waypoints for a unit moving at unit_speed.
+        Returns total exposure, peak influence, time spent above threshold and the index of the worst segment.
+        Raises ValueError if unit_speed isn't above 0.
+        """
+        return self._map.path_exposure(map_type, path, unit_speed, threshold)
+
     def safest_spot(
         self, map_type: MapType, destination_center: Tuple[float, float], walk_distance: float
     ) -> Tuple[Tuple[int, int], float]:
@@ -223,26 +533,143 @@ class Sc2Map:
     def clear_vision(self) -> None:
         self._map.clear_vision()
 
-    def add_vision_params(self, detector: bool, flying: bool, position: Tuple[float, float], sight_range: float) -> None:
-        vision_unit = VisionUnit(detector, flying, position, sight_range)
+    def add_vision_params(
+        self,
+        detector: bool,
+        flying: bool,
+        position: Tuple[float, float],
+        sight_range: float,
+        detection_range: Optional[float] = None,
+    ) -> None:
+        vision_unit = VisionUnit(detector, flying, position, sight_range, detection_range)
         self.add_vision(vision_unit)
 
     def add_vision_unit(self, unit: Unit) -> "VisionUnit":
-        vision_unit = VisionUnit(unit.detect_range > 0, unit.is_flying, unit.position_tuple, unit.sight_range)
+        detection_range = unit.detect_range if unit.detect_range > 0 else None
+        vision_unit = VisionUnit(
+            detection_range is not None, unit.is_flying, unit.position_tuple, unit.sight_range, detection_range
+        )
         self.add_vision(vision_unit)
 
     def add_vision(self, vision_unit: VisionUnit) -> None:
         self._map.add_vision_unit(vision_unit)
 
+    def set_vision_unit(self, unit: Unit) -> None:
+        """
+        Adds or updates vision of a unit by its tag. Only the cells affected by the unit are recalculated,
+        so this can be called every frame for moving units without clearing vision.
+        """
+        detection_range = unit.detect_range if unit.detect_range > 0 else None
+        vision_unit = VisionUnit(
+            detection_range is not None, unit.is_flying, unit.position_tuple, unit.sight_range, detection_range
+        )
+        self._map.set_vision_unit(unit.tag, vision_unit)
+
+    def move_vision_unit(self, tag: int, position: Tuple[float, float]) -> bool:
+        return self._map.move_vision_unit(tag, position)
+
+    def remove_vision_unit(self, tag: int) -> bool:
+        return self._map.remove_vision_unit(tag)
+
+    def set_los_blockers(self, blockers: np.ndarray) -> None:
+        """
+        Sets line of sight blocker grid, values above 0 block ground vision.
+        Raises ValueError if the grid isn't the size of the map.
+        """
+        self._map.set_los_blockers(np.swapaxes(blockers, 0, 1))
+
+    def set_watchtowers(self, towers: List[Tuple[Tuple[float, float], float]]) -> None:
+        """
+        Sets Xel'Naga watchtowers as a list of positions and vision radii.
+        """
+        self._map.set_watchtowers(towers)
+
+    def set_watchtower_occupied(self, position: Tuple[float, float], occupied: bool) -> bool:
+        """
+        Occupied watchtowers are included in the vision map.
+        """
+        return self._map.set_watchtower_occupied(position, occupied)
+
     def calculate_vision(self) -> None:
         self._map.calculate_vision_map()
 
+    def clear_enemy_vision(self) -> None:
+        self._map.clear_enemy_vision()
+
+    def add_enemy_vision_unit(self, unit: Unit) -> None:
+        """
+        Adds estimated enemy vision, use snapshot or remembered enemy units.
+        """
+        detection_range = unit.detect_range if unit.detect_range > 0 else None
+        vision_unit = VisionUnit(
+            detection_range is not None, unit.is_flying, unit.position_tuple, unit.sight_range, detection_range
+        )
+        self._map.add_enemy_vision_unit(vision_unit)
+
+    def calculate_enemy_vision(self) -> None:
+        self._map.calculate_enemy_vision_map()
+
+    def enemy_vision_status(self, position: Tuple[float, float]) -> VisionStatus:
+        return self._map.enemy_vision_status(position)
+
+    def find_path_stealth(
+        self,
+        map_type: MapType,
+        start: Tuple[float, float],
+        end: Tuple[float, float],
+        seen_cost: int,
+        detection_cost: int,
+    ) -> Tuple[List[Tuple[int, int]], float]:
+        """
+        Finds the least observed path through estimated enemy vision.
+        Cells that are only seen cost `seen_cost`, detected cells cost `detection_cost` whether they are seen or not.
+        For cloaked units use low `seen_cost` and high `detection_cost`.
+
+        :return: Tuple of points and total influenced distance.
+        """
+        return self._map.find_path_stealth(map_type, start, end, seen_cost, detection_cost, self.heuristic_accuracy)
+
     def add_influence_to_vision(self, map_type: MapType, seen_value: int, detection_value: int) -> None:
         """
         Adds influence to enemy vision.
+        Cells that are only seen get `seen_value`, detected cells get `detection_value` whether they are seen or not.
         """
         self._map.add_influence_to_vision(map_type, seen_value, detection_value)
 
+    def update_vision_memory(self, game_loop: int) -> None:
+        """
+        Marks currently seen or detected cells as seen on this game loop.
+        Call this after vision is calculated for the frame.
+        """
+        self._map.update_vision_memory(game_loop)
+
+    def vision_staleness(self, position: Tuple[float, float], game_loop: int) -> int:
+        """
+        Game loops since the position was last seen or detected.
+        """
+        return self._map.vision_staleness(position, game_loop)
+
+    def cells_not_seen_for(self, game_loop: int, loops: int) -> List[Tuple[int, int]]:
+        return self._map.cells_not_seen_for(game_loop, loops)
+
+    def oldest_seen_cells(
+        self, center: Tuple[float, float], distance: float, count: int = 1
+    ) -> List[Tuple[Tuple[int, int], int]]:
+        """
+        Finds the cells within distance that were seen the longest time ago.
+        :return: List of positions and game loops they were last seen on
+        """
+        return self._map.oldest_seen_cells(center, distance, count)
+
+    def add_influence_by_staleness(
+        self, map_type: MapType, game_loop: int, max_value: float, full_staleness: int
+    ) -> None:
+        """
+        Adds influence that grows with the time since the cell was last seen.
+        Influence reaches `max_value` when the cell hasn't been seen for `full_staleness` game loops.
+        """
+        self._map.add_influence_by_staleness(map_type, game_loop, max_value, full_staleness)
+
     def vision_status(self, position: Tuple[float, float]) -> VisionStatus:
         """
         Get vision status for a position
@@ -262,7 +689,7 @@ class Sc2Map:
         """
 
         image = np.array(self._map.vision_map, dtype=np.uint8)
-        image = np.multiply(image, 120)
+        image = np.multiply(image, 80)
         self.plot_image(image, image_name, resize)
 
     def plot(self, image_name: str = "map", resize: int = 4):
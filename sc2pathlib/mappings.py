@@ -15,4 +15,16 @@ class MapType(IntEnum):
 class VisionStatus(IntEnum):
     NotSeen = 0
     Seen = 1
-    Detected = 2
\ No newline at end of file
+    Detected = 2
+    NotSeenButDetected = 3
+
+
+class FormationType(IntEnum):
+    Line = 0
+    Box = 1
+    Wedge = 2
+
+
+class TravelMode(IntEnum):
+    Walk = 0
+    Fly = 1
@@ -1,4 +1,4 @@
-from .sc2pathlib import PathFind
+from .sc2pathlib import PathFind, InfluenceShape, PathExposure
 
 import numpy as np
 from typing import Union, List, Tuple, Optional
@@ -67,6 +67,20 @@ class PathFinder:
         else:
             self._path_find.remove_block(center, size)
 
+    def is_reachable(self, start: Tuple[float, float], end: Tuple[float, float]) -> bool:
+        """
+        Checks whether a path exists between the points without searching for it.
+        Blocked points are never reachable.
+        """
+        return self._path_find.is_reachable(start, end)
+
+    def component(self, point: Tuple[float, float]) -> int:
+        """
+        Connected component of the point, points with the same component can reach each other.
+        0 for points that aren't pathable.
+        """
+        return self._path_find.component(point)
+
     def find_path(
         self, start: Tuple[float, float], end: Tuple[float, float],
         large: bool = False,
@@ -98,6 +112,67 @@ class PathFinder:
     ) -> Tuple[List[Tuple[int, int]], float]:
         return self.find_path(start, end, large, influence=True)
 
+    def find_path_constrained(
+        self,
+        start: Tuple[float, float],
+        end: Tuple[float, float],
+        max_influence: Optional[int] = None,
+        exposure_budget: Optional[float] = None,
+    ) -> Optional[Tuple[List[Tuple[int, int]], float]]:
+        """
+        Finds the shortest path that never enters a cell with influence above max_influence
+        and keeps total exposure (influence multiplied by distance) under exposure_budget.
+        The search is approximate and bounded, a path that barely fits the budget may be missed.
+        Returns None when no such path is found.
+        """
+        start_int = (round(start[0]), round(start[1]))
+        end_int = (round(end[0]), round(end[1]))
+        return self._path_find.find_path_constrained(start_int, end_int, max_influence, exposure_budget)
+
+    def find_paths_cooperative(
+        self,
+        starts: List[Tuple[float, float]],
+        goals: List[Tuple[float, float]],
+        window: Optional[int] = None,
+        radii: Optional[List[float]] = None,
+    ) -> List[List[Tuple[int, int]]]:
+        """
+        Finds paths for a batch of units that don't run into each other, earlier units have priority.
+        Each path has one cell per time step and repeated cells mean the unit waits.
+        A unit blocked in by others stops as close to its goal as it can get.
+        window is how many steps units plan ahead around each other, radii are unit sizes.
+        Larger units only move where they fit and stop at the closest cell to the goal where they fit.
+        Raises ValueError when starts and goals have different lengths.
+        """
+        starts_int = [(round(p[0]), round(p[1])) for p in starts]
+        goals_int = [(round(p[0]), round(p[1])) for p in goals]
+        return self._path_find.find_paths_cooperative(starts_int, goals_int, window, radii)
+
+    def find_flee_path(
+        self, start: Tuple[float, float], threats: List[Tuple[float, float]], distance: float
+    ) -> Tuple[List[Tuple[int, int]], float]:
+        """
+        Finds a path within walking distance that gets away from the threats.
+        Routes through influence and destinations in corners or dead ends are penalized.
+        """
+        return self._path_find.find_flee_path(start, threats, distance)
+
+    def influence_gradient(self, position: Tuple[float, float], radius: float) -> Tuple[float, float]:
+        """
+        Unit vector pointing towards decreasing influence, smoothed over pathable cells within radius.
+        Returns (0, 0) when influence is flat.
+        """
+        return self._path_find.influence_gradient(position, radius)
+
+    def path_exposure(
+        self, path: List[Tuple[float, float]], unit_speed: float, threshold: Optional[int] = None
+    ) -> PathExposure:
+        """
+        Integrates influence along a path of cells or waypoints for a unit moving at unit_speed.
+        Raises ValueError if unit_speed isn't above 0.
+        """
+        return self._path_find.path_exposure(path, unit_speed, threshold)
+
     def safest_spot(self, destination_center: Tuple[float, float], walk_distance: float) -> Tuple[Tuple[int, int], float]:
         destination_int = (round(destination_center[0]), round(destination_center[1]))
         return self._path_find.lowest_influence_walk(destination_int, walk_distance)
@@ -126,6 +201,13 @@ class PathFinder:
         else:
             self._path_find.add_walk_influence(list, value, distance)
 
+    def add_influence_shape(self, shape: InfluenceShape, value: float, fade: float = 0):
+        """
+        Adds influence inside a line, cone, capsule or rectangle shape.
+        With fade above zero the influence fades out over that distance from the edge of the shape.
+        """
+        self._path_find.add_influence_shape(shape, value, fade)
+
     def find_low_inside_walk(
         self, start: Tuple[float, float], target: Tuple[float, float], distance: Union[int, float]
     ) -> (Tuple[float, float], float):
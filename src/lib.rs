@@ -9,7 +9,14 @@ pub mod path_find;
 #[pymodule]
 fn sc2pathlib(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<path_find::PathFind>()?;
+    m.add_class::<path_find::shapes::InfluenceShape>()?;
+    m.add_class::<path_find::exposure::PathExposure>()?;
     m.add_class::<mapping::map::Map>()?;
     m.add_class::<mapping::vision::VisionUnit>()?;
+    m.add_class::<mapping::overlord_spots::OverlordSpot>()?;
+    m.add_class::<mapping::threats::ThreatUnit>()?;
+    m.add_class::<mapping::islands::Island>()?;
+    m.add_class::<mapping::drop_spots::DropSpot>()?;
+    m.add_class::<mapping::transport::TransportLeg>()?;
     Ok(())
 }
@@ -0,0 +1,105 @@
+use std::collections::{HashMap, HashSet};
+
+use pathfinding::prelude::dijkstra_partial;
+use pyo3::prelude::*;
+
+use crate::helpers::round_point2;
+use crate::path_find::pos::{self, NormalPosAPI, Pos, PositionAPI};
+
+use super::map::Map;
+use super::vision::{calc_ground_vision, max_height_seen};
+
+// Score bonus for high ground cells that see the target, in cells of travel distance
+const HIGH_GROUND_BONUS: f32 = 5.0;
+const MAP_TYPE_AIR: u8 = 3;
+
+#[pymethods]
+impl Map {
+    /// Finds cells from which a unit can hit the target, best first as (position, score).
+    /// Weapon range is measured from the edge of the unit, add target radius to it when needed.
+    /// Score is current influence plus travel distance from start and lower is better.
+    /// High ground cells that see the target get a bonus. Without start travel distance and reachability are ignored.
+    pub fn find_attack_positions(&self,
+                                 map_type: u8,
+                                 target: (f32, f32),
+                                 weapon_range: f32,
+                                 unit_radius: f32,
+                                 count: usize,
+                                 start: Option<(f32, f32)>)
+                                 -> Vec<((usize, usize), f32)> {
+        let map = self.get_map(map_type);
+        let reach = weapon_range + unit_radius;
+        let target_cell = round_point2(target);
+        let r = reach.ceil() as i64;
+        let mut candidates: Vec<(usize, usize)> = Vec::new();
+
+        for x in i64::max(0, target_cell.0 as i64 - r)..i64::min(map.width as i64, target_cell.0 as i64 + r + 1) {
+            for y in i64::max(0, target_cell.1 as i64 - r)..i64::min(map.height as i64, target_cell.1 as i64 + r + 1) {
+                let dx = x as f32 - target.0;
+                let dy = y as f32 - target.1;
+                if map.map[x as usize][y as usize] > 0 && dx * dx + dy * dy <= reach * reach {
+                    candidates.push((x as usize, y as usize));
+                }
+            }
+        }
+
+        let travel = start.map(|s| travel_distances(&map.map, round_point2(s), &candidates));
+        let target_level = max_height_seen(&self.points, target_cell);
+        let mut result: Vec<((usize, usize), f32)> = Vec::with_capacity(candidates.len());
+
+        for cell in candidates {
+            let mut score = map.map[cell.0][cell.1] as f32;
+
+            if let Some(distances) = &travel {
+                match distances.get(&cell) {
+                    Some(d) => score += d,
+                    None => continue,
+                }
+            }
+
+            if map_type != MAP_TYPE_AIR
+               && max_height_seen(&self.points, cell) > target_level
+               && self.sees_cell(cell, target_cell, reach + 1.0)
+            {
+                score -= HIGH_GROUND_BONUS * map.normal_influence as f32;
+            }
+
+            result.push((cell, score));
+        }
+
+        result.sort_by(|a, b| a.1.total_cmp(&b.1));
+        result.truncate(count);
+        result
+    }
+}
+
+impl Map {
+    /// Whether a ground unit at the cell sees the target cell
+    fn sees_cell(&self, cell: (usize, usize), target: (usize, usize), sight_range: f32) -> bool {
+        let mut seen = false;
+        calc_ground_vision(&self.points, &(cell.0 as f32, cell.1 as f32), sight_range, |x, y| {
+            if (x, y) == target {
+                seen = true;
+            }
+        });
+        seen
+    }
+}
+
+/// Walking distances from start to the reachable candidates
+fn travel_distances(grid: &[Vec<usize>],
+                    start: (usize, usize),
+                    candidates: &[(usize, usize)])
+                    -> HashMap<(usize, usize), f32> {
+    let api = NormalPosAPI();
+    let start = Pos(start.0, start.1);
+    let mut remaining: HashSet<(usize, usize)> = candidates.iter().cloned().collect();
+    let (parents, _) = dijkstra_partial(&start,
+                                        |p| api.successors(p, grid),
+                                        |p| remaining.remove(&(p.0, p.1)) && remaining.is_empty());
+
+    let mut distances: HashMap<(usize, usize), f32> =
+        parents.iter().map(|(p, (_, cost))| ((p.0, p.1), *cost as f32 / pos::MULTF32)).collect();
+    distances.insert((start.0, start.1), 0.0);
+    distances
+}
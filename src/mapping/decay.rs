@@ -0,0 +1,227 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::helpers::round_point2;
+use crate::path_find::rectangle;
+
+use super::map::Map;
+
+/// Largest influence a layer adds to a cell, keeps the pathing values from overflowing
+const MAX_APPLIED_VALUE: f32 = 65535.0;
+
+/// Influence that is remembered between frames and halves every half life game loops.
+pub struct DecayLayer {
+    pub values: Vec<Vec<f32>>,
+    /// Integer influence currently added to the pathing maps
+    applied: Vec<Vec<usize>>,
+    pub half_life: f32,
+    pub map_type: usize,
+    pub game_loop: u32,
+}
+
+impl DecayLayer {
+    pub fn new(width: usize, height: usize, half_life: f32, map_type: usize, game_loop: u32) -> Self {
+        DecayLayer { values: vec![vec![0.0; height]; width],
+                     applied: vec![vec![0; height]; width],
+                     half_life,
+                     map_type,
+                     game_loop }
+    }
+
+    /// Decays all values to the game loop
+    pub fn decay(&mut self, game_loop: u32) {
+        if game_loop <= self.game_loop {
+            return;
+        }
+
+        let factor = 0.5f32.powf((game_loop - self.game_loop) as f32 / self.half_life);
+        self.game_loop = game_loop;
+
+        for column in self.values.iter_mut() {
+            for value in column.iter_mut() {
+                *value *= factor;
+            }
+        }
+    }
+
+    /// Moves values towards the observed influence by blend, 1 replaces the old values.
+    /// Observed influence fades out linearly from max at the positions to zero at distance.
+    pub fn observe(&mut self, positions: &[(f32, f32)], max: f32, distance: f32, blend: f32) {
+        let width = self.values.len();
+        let height = self.values[0].len();
+        let blend = blend.clamp(0.0, 1.0);
+        let mut observed = vec![vec![0f32; height]; width];
+        let diameter = ((distance * 2f32) as usize) + 2;
+
+        for position in positions {
+            let position = round_point2(*position);
+            let rect = rectangle::Rectangle::init_from_center2(position, (diameter, diameter), width, height);
+
+            for (x, column) in observed.iter_mut().enumerate().take(rect.x_end).skip(rect.x) {
+                for (y, cell) in column.iter_mut().enumerate().take(rect.y_end).skip(rect.y) {
+                    let dx = x as f32 - position.0 as f32;
+                    let dy = y as f32 - position.1 as f32;
+                    let value = max * (1.0 - (dx * dx + dy * dy).sqrt() / distance);
+                    *cell = f32::max(*cell, value);
+                }
+            }
+        }
+
+        for (column, observed_column) in self.values.iter_mut().zip(observed.iter()) {
+            for (value, observed_value) in column.iter_mut().zip(observed_column.iter()) {
+                if *observed_value > 0.0 {
+                    *value += (*observed_value - *value) * blend;
+                }
+            }
+        }
+    }
+
+    /// Adds the currently applied influence to pathable cells inside the rectangle,
+    /// used when the cells have been reset without the layer.
+    fn apply_within(&self, maps: &mut [&mut Vec<Vec<usize>>], rect: &rectangle::Rectangle) {
+        for x in rect.x..rect.x_end {
+            for y in rect.y..rect.y_end {
+                for map in maps.iter_mut() {
+                    if map[x][y] > 0 {
+                        map[x][y] += self.applied[x][y];
+                    }
+                }
+            }
+        }
+    }
+
+    /// Replaces the previously applied influence on the maps with the current values.
+    fn sync(&mut self, maps: &mut [&mut Vec<Vec<usize>>]) {
+        for (x, column) in self.applied.iter_mut().enumerate() {
+            for (y, applied) in column.iter_mut().enumerate() {
+                let new_value = self.values[x][y].clamp(0.0, MAX_APPLIED_VALUE) as usize;
+                if new_value == *applied {
+                    continue;
+                }
+
+                for map in maps.iter_mut() {
+                    let cell = &mut map[x][y];
+                    if *cell > 0 {
+                        *cell = usize::max(1, cell.saturating_sub(*applied)).saturating_add(new_value);
+                    }
+                }
+                *applied = new_value;
+            }
+        }
+    }
+}
+
+#[pymethods]
+impl Map {
+    /// Creates or replaces a persistent influence layer that halves every half_life game loops.
+    /// map_type uses the same values as `add_influence_fading`.
+    pub fn add_decay_layer(&mut self, name: String, half_life: f32, map_type: usize) -> PyResult<()> {
+        if half_life.is_nan() || half_life <= 0.0 {
+            return Err(PyValueError::new_err(format!("Half life must be above 0, got {}", half_life)));
+        }
+
+        self.remove_decay_layer(&name);
+        let layer =
+            DecayLayer::new(self.ground_pathing.width, self.ground_pathing.height, half_life, map_type, self.game_loop);
+        self.decay_layers.insert(name, layer);
+        Ok(())
+    }
+
+    /// Removes the layer and its influence from the maps
+    pub fn remove_decay_layer(&mut self, name: &str) -> bool {
+        if let Some(mut layer) = self.decay_layers.remove(name) {
+            for column in layer.values.iter_mut() {
+                column.fill(0.0);
+            }
+            self.sync_decay_layer(&mut layer);
+            return true;
+        }
+        false
+    }
+
+    /// Blends new observations into the layer, returns false if the layer doesn't exist.
+    pub fn observe_decay_layer(&mut self,
+                               name: &str,
+                               positions: Vec<(f32, f32)>,
+                               max: f32,
+                               distance: f32,
+                               blend: f32)
+                               -> PyResult<bool> {
+        if distance.is_nan() || distance <= 0.0 {
+            return Err(PyValueError::new_err(format!("Distance must be above 0, got {}", distance)));
+        }
+
+        if let Some(mut layer) = self.decay_layers.remove(name) {
+            layer.decay(self.game_loop);
+            layer.observe(&positions, max, distance, blend);
+            self.sync_decay_layer(&mut layer);
+            self.decay_layers.insert(name.to_string(), layer);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Current value of the layer at the point, 0 if the layer doesn't exist.
+    pub fn decay_influence(&self, name: &str, point: (f32, f32)) -> f32 {
+        let point = round_point2(point);
+        match self.decay_layers.get(name) {
+            Some(layer) => layer.values[point.0][point.1],
+            None => 0.0,
+        }
+    }
+
+    /// All values of the layer, None if the layer doesn't exist.
+    pub fn decay_layer(&self, name: &str) -> Option<Vec<Vec<f32>>> {
+        self.decay_layers.get(name).map(|layer| layer.values.clone())
+    }
+}
+
+impl Map {
+    fn sync_decay_layer(&mut self, layer: &mut DecayLayer) {
+        let mut maps: Vec<&mut Vec<Vec<usize>>> =
+            self.get_influence_maps(layer.map_type).into_iter().map(|m| &mut m.map).collect();
+        layer.sync(&mut maps);
+    }
+
+    /// Decays all layers to the game loop and updates their influence on the maps.
+    pub fn decay_all_layers(&mut self, game_loop: u32) {
+        let names: Vec<String> = self.decay_layers.keys().cloned().collect();
+
+        for name in names {
+            if let Some(mut layer) = self.decay_layers.remove(&name) {
+                layer.decay(game_loop);
+                self.sync_decay_layer(&mut layer);
+                self.decay_layers.insert(name, layer);
+            }
+        }
+    }
+
+    /// Applies all layers again to the cells inside the rectangle after they have been reset.
+    pub fn apply_decay_layers_within(&mut self, rect: &rectangle::Rectangle) {
+        let names: Vec<String> = self.decay_layers.keys().cloned().collect();
+
+        for name in names {
+            if let Some(layer) = self.decay_layers.remove(&name) {
+                let mut maps: Vec<&mut Vec<Vec<usize>>> =
+                    self.get_timed_block_influence_maps(layer.map_type).into_iter().map(|m| &mut m.map).collect();
+                layer.apply_within(&mut maps, rect);
+                self.decay_layers.insert(name, layer);
+            }
+        }
+    }
+
+    /// Applies all layers again after the maps have been reset.
+    pub fn apply_decay_layers(&mut self) {
+        let names: Vec<String> = self.decay_layers.keys().cloned().collect();
+
+        for name in names {
+            if let Some(mut layer) = self.decay_layers.remove(&name) {
+                for column in layer.applied.iter_mut() {
+                    column.fill(0);
+                }
+                self.sync_decay_layer(&mut layer);
+                self.decay_layers.insert(name, layer);
+            }
+        }
+    }
+}
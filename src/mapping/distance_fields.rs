@@ -0,0 +1,109 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use pyo3::prelude::*;
+
+use crate::helpers::round_point2;
+use crate::path_find::pos::{self, NormalPosAPI, Pos, PositionAPI};
+
+use super::map::Map;
+
+/// Walking distances from the closest of the sources to every cell, calculated once and cached.
+pub struct DistanceField {
+    /// Distances multiplied by pos::MULT, usize::MAX for unreachable cells
+    pub distances: Vec<Vec<usize>>,
+    pub map_type: u8,
+}
+
+impl DistanceField {
+    /// Multi-source Dijkstra over the pathable cells of the grid
+    pub fn new(grid: &[Vec<usize>], sources: &[(usize, usize)], map_type: u8) -> Self {
+        let api = NormalPosAPI();
+        let mut distances = vec![vec![usize::MAX; grid[0].len()]; grid.len()];
+        let mut queue = BinaryHeap::new();
+
+        for source in sources {
+            if grid[source.0][source.1] > 0 {
+                distances[source.0][source.1] = 0;
+                queue.push(Reverse((0, Pos(source.0, source.1))));
+            }
+        }
+
+        while let Some(Reverse((distance, position))) = queue.pop() {
+            if distance > distances[position.0][position.1] {
+                continue;
+            }
+
+            for (next, cost) in api.successors(&position, grid) {
+                let next_distance = distance + cost;
+                if next_distance < distances[next.0][next.1] {
+                    distances[next.0][next.1] = next_distance;
+                    queue.push(Reverse((next_distance, next)));
+                }
+            }
+        }
+
+        DistanceField { distances,
+                        map_type }
+    }
+
+    /// Distance at the cell, None if it can't reach any source
+    pub fn distance(&self, cell: (usize, usize)) -> Option<f32> {
+        match self.distances.get(cell.0).and_then(|c| c.get(cell.1)) {
+            Some(d) if *d != usize::MAX => Some(*d as f32 / pos::MULTF32),
+            _ => None,
+        }
+    }
+
+    /// Follows decreasing distances from the cell to the closest source
+    pub fn path(&self, grid: &[Vec<usize>], cell: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+        self.distance(cell)?;
+        let api = NormalPosAPI();
+        let mut current = Pos(cell.0, cell.1);
+        let mut path = vec![cell];
+
+        while self.distances[current.0][current.1] > 0 {
+            let next =
+                api.successors(&current, grid).into_iter().map(|(p, _)| p).min_by_key(|p| self.distances[p.0][p.1])?;
+            if self.distances[next.0][next.1] >= self.distances[current.0][current.1] {
+                // The map has changed since the field was built
+                return None;
+            }
+
+            path.push((next.0, next.1));
+            current = next;
+        }
+
+        Some(path)
+    }
+}
+
+#[pymethods]
+impl Map {
+    /// Calculates walking distances from the closest of the sources to every cell and stores them by name.
+    /// Build the field again when pathing changes, lookups use the map as it was when the field was built.
+    pub fn build_distance_field(&mut self, name: String, sources: Vec<(f32, f32)>, map_type: u8) {
+        let map = self.get_map(map_type);
+        let sources: Vec<(usize, usize)> =
+            sources.into_iter().map(|s| map.get_closest_pathable(round_point2(s))).collect();
+        let field = DistanceField::new(&map.map, &sources, map_type);
+        self.distance_fields.insert(name, field);
+    }
+
+    /// Removes the distance field, returns false if it doesn't exist.
+    pub fn remove_distance_field(&mut self, name: &str) -> bool { self.distance_fields.remove(name).is_some() }
+
+    /// Walking distance from the point to the closest source of the field.
+    /// None if the field doesn't exist or the point can't reach any source.
+    pub fn distance_from(&self, name: &str, point: (f32, f32)) -> Option<f32> {
+        self.distance_fields.get(name)?.distance(round_point2(point))
+    }
+
+    /// Shortest path from the point to the closest source of the field.
+    /// None if the field doesn't exist or the point can't reach any source.
+    pub fn path_from(&self, name: &str, point: (f32, f32)) -> Option<Vec<(usize, usize)>> {
+        let field = self.distance_fields.get(name)?;
+        let map = self.get_map(field.map_type);
+        field.path(&map.map, round_point2(point))
+    }
+}
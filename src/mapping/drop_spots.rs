@@ -0,0 +1,154 @@
+use std::collections::HashSet;
+
+use pathfinding::prelude::{build_path, dijkstra_partial};
+use pyo3::prelude::*;
+
+use crate::helpers::round_point2;
+use crate::path_find::euclidean_distance;
+use crate::path_find::pos::{InfluencedPosAPI, Pos, PositionAPI};
+
+use super::map::Map;
+
+const DEFAULT_SEARCH_RADIUS: f32 = 10.0;
+// Cells within this height of the target are on the same level
+const DIFFERENCE: usize = 12;
+// Score penalty for landing in estimated enemy vision, in cells of distance
+const SEEN_PENALTY: f32 = 10.0;
+// Landing closer than this to a defense is penalized
+const DEFENSE_RANGE: f32 = 10.0;
+// Score penalty per cell inside defense range
+const DEFENSE_PENALTY: f32 = 2.0;
+// Spots closer than this to a better spot are skipped
+const DROP_SPOT_SPACING: f32 = 3.0;
+
+/// Landing spot for a drop with the air path to it
+#[pyclass]
+#[derive(Clone)]
+pub struct DropSpot {
+    pub position: (usize, usize),
+    pub score: f32,
+    pub exposure: f32,
+    pub seen: bool,
+    pub defense_distance: Option<f32>,
+    pub air_path: Vec<(usize, usize)>,
+}
+
+#[pymethods]
+impl DropSpot {
+    #[getter(position)]
+    fn get_position(&self) -> (usize, usize) { self.position }
+
+    /// Lower is better
+    #[getter(score)]
+    fn get_score(&self) -> f32 { self.score }
+
+    /// Air influence above normal influence integrated along the air path
+    #[getter(exposure)]
+    fn get_exposure(&self) -> f32 { self.exposure }
+
+    /// Whether the spot is in estimated enemy vision
+    #[getter(seen)]
+    fn get_seen(&self) -> bool { self.seen }
+
+    /// Distance to the closest defense, None without defenses
+    #[getter(defense_distance)]
+    fn get_defense_distance(&self) -> Option<f32> { self.defense_distance }
+
+    /// Recommended air path from the start to the spot
+    #[getter(air_path)]
+    fn get_air_path(&self) -> Vec<(usize, usize)> { self.air_path.clone() }
+}
+
+#[pymethods]
+impl Map {
+    /// Finds landing spots for a drop near the target, such as the mineral line, best first.
+    /// Spots are ground pathable cells on the height level of the target within radius, 10 by default.
+    /// The target can be a mineral field, its level is taken from the closest pathable cell.
+    /// Score is distance to the target plus air path exposure from start relative to normal influence,
+    /// with penalties for estimated enemy vision and for landing within range of the defenses.
+    pub fn find_drop_spots(&self,
+                           target: (f32, f32),
+                           start: (f32, f32),
+                           defenses: Vec<(f32, f32)>,
+                           count: usize,
+                           radius: Option<f32>)
+                           -> Vec<DropSpot> {
+        let radius = radius.unwrap_or(DEFAULT_SEARCH_RADIUS);
+        let ground = &self.ground_pathing;
+        let air = &self.air_pathing;
+        let target_cell = ground.get_closest_pathable(round_point2(target));
+        let target_height = self.points[target_cell.0][target_cell.1].height;
+        let r = radius.ceil() as i64;
+        let mut candidates: Vec<(usize, usize)> = Vec::new();
+
+        for x in i64::max(0, target_cell.0 as i64 - r)..i64::min(ground.width as i64, target_cell.0 as i64 + r + 1) {
+            for y in i64::max(0, target_cell.1 as i64 - r)..i64::min(ground.height as i64, target_cell.1 as i64 + r + 1)
+            {
+                let cell = (x as usize, y as usize);
+                if ground.map[cell.0][cell.1] > 0
+                   && self.points[cell.0][cell.1].height.abs_diff(target_height) <= DIFFERENCE
+                   && euclidean_distance(target, (x as f32, y as f32)) <= radius
+                {
+                    candidates.push(cell);
+                }
+            }
+        }
+
+        let start = air.get_closest_pathable(round_point2(start));
+        let start_pos = Pos(start.0, start.1);
+        let api = InfluencedPosAPI { normal_influence: air.normal_influence };
+        let mut remaining: HashSet<(usize, usize)> = candidates.iter().cloned().collect();
+        remaining.remove(&start);
+        let (parents, _) = dijkstra_partial(&start_pos,
+                                            |p| api.successors(p, &air.map),
+                                            |p| remaining.remove(&(p.0, p.1)) && remaining.is_empty());
+
+        let mut spots: Vec<DropSpot> = Vec::with_capacity(candidates.len());
+        for cell in candidates {
+            let position = Pos(cell.0, cell.1);
+            if cell != start && !parents.contains_key(&position) {
+                continue;
+            }
+
+            let air_path: Vec<(usize, usize)> = build_path(&position, &parents).iter().map(|p| (p.0, p.1)).collect();
+            let path_points: Vec<(f32, f32)> = air_path.iter().map(|p| (p.0 as f32, p.1 as f32)).collect();
+            let exposure = air.exposure_along(&path_points, 1.0, 0).total;
+            let point = (cell.0 as f32, cell.1 as f32);
+            let seen = matches!(self.enemy_vision_map.vision_status(point), 1 | 2);
+            let defense_distance = defenses.iter().map(|d| euclidean_distance(*d, point)).reduce(f32::min);
+
+            let mut score = euclidean_distance(target, point) + exposure / air.normal_influence as f32;
+            if seen {
+                score += SEEN_PENALTY;
+            }
+            if let Some(d) = defense_distance {
+                score += f32::max(0.0, DEFENSE_RANGE - d) * DEFENSE_PENALTY;
+            }
+
+            spots.push(DropSpot { position: cell,
+                                  score,
+                                  exposure,
+                                  seen,
+                                  defense_distance,
+                                  air_path });
+        }
+
+        spots.sort_by(|a, b| a.score.total_cmp(&b.score));
+        let mut result: Vec<DropSpot> = Vec::new();
+
+        for spot in spots {
+            let point = (spot.position.0 as f32, spot.position.1 as f32);
+            if result.iter().all(|s| {
+                             euclidean_distance((s.position.0 as f32, s.position.1 as f32), point) >= DROP_SPOT_SPACING
+                         })
+            {
+                result.push(spot);
+                if result.len() == count {
+                    break;
+                }
+            }
+        }
+
+        result
+    }
+}
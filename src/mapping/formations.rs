@@ -0,0 +1,375 @@
+use std::collections::HashSet;
+
+use pathfinding::kuhn_munkres::kuhn_munkres_min;
+use pathfinding::matrix::Matrix;
+use pyo3::prelude::*;
+
+use crate::helpers::round_point2;
+use crate::path_find::{euclidean_distance, PathFind};
+
+use super::map::Map;
+
+// Extra rows behind the arc when walls block slots
+const MAX_ROWS: usize = 4;
+// Slots are at most this angle to either side of the direction from the target to the units
+const MAX_ARC_ANGLE: f32 = std::f32::consts::FRAC_PI_2;
+// Travel distances are rounded to this precision for the assignment
+const ASSIGNMENT_MULT: f32 = 1000.0;
+// Step used when measuring free space to the sides of the path
+const CLEARANCE_STEP: f32 = 0.5;
+// Waypoints on each side whose clearance limits the formation width, so it compresses before a choke
+const CLEARANCE_WINDOW: usize = 3;
+
+pub const FORMATION_LINE: u8 = 0;
+pub const FORMATION_BOX: u8 = 1;
+pub const FORMATION_WEDGE: u8 = 2;
+
+#[pymethods]
+impl Map {
+    /// Finds non-overlapping ground positions on an arc facing the target, one for each (position, radius) unit.
+    /// Arc is at range from the target towards the units, spans at most a half circle
+    /// and units are assigned to slots to minimize total travel.
+    /// Slots behind walls or chokes are skipped and extra rows are added behind the arc when needed.
+    /// Units that didn't get a slot get None.
+    pub fn concave_positions(&self,
+                             units: Vec<((f32, f32), f32)>,
+                             target: (f32, f32),
+                             range: f32)
+                             -> Vec<Option<(f32, f32)>> {
+        if units.is_empty() {
+            return Vec::new();
+        }
+
+        let count = units.len() as f32;
+        let center =
+            (units.iter().map(|u| u.0 .0).sum::<f32>() / count, units.iter().map(|u| u.0 .1).sum::<f32>() / count);
+        let max_radius = units.iter().map(|u| u.1).fold(0.0, f32::max);
+        let spacing = f32::max(1.0, max_radius * 2.0);
+        let slots = self.concave_slots(center, target, range + max_radius, spacing, max_radius, units.len());
+
+        let positions: Vec<(f32, f32)> = units.iter().map(|u| u.0).collect();
+        assign_slots(&positions, &slots).into_iter().map(|slot| slot.map(|s| slots[s])).collect()
+    }
+
+    /// Offsets the path for each unit to keep a formation: 0 line, 1 box or 2 wedge, with spacing between units.
+    /// Formation is compressed sideways where the terrain is narrower and expands again afterwards,
+    /// units whose slot is still blocked move to the closest free cell that no other unit uses.
+    /// Units are assigned to formation slots minimizing travel and the paths are in the same order as the units.
+    pub fn formation_paths(&self,
+                           map_type: u8,
+                           units: Vec<(f32, f32)>,
+                           path: Vec<(f32, f32)>,
+                           formation: u8,
+                           spacing: f32)
+                           -> Vec<Vec<(f32, f32)>> {
+        if units.is_empty() || path.is_empty() {
+            return vec![path; units.len()];
+        }
+
+        let pathing = self.get_map(map_type);
+        let offsets = formation_offsets(formation, units.len(), spacing);
+        let half_width = offsets.iter().map(|o| o.1.abs()).fold(0.0, f32::max);
+        let lengths = cumulative_lengths(&path);
+
+        let scales = formation_scales(pathing, &path, half_width);
+        let slots: Vec<(f32, f32)> = offsets.iter().map(|o| offset_point(&path, &lengths, 0, o.0, o.1)).collect();
+
+        let unit_offsets: Vec<(f32, f32)> =
+            assign_slots(&units, &slots).into_iter().map(|slot| slot.map_or((0.0, 0.0), |s| offsets[s])).collect();
+        let mut unit_paths: Vec<Vec<(f32, f32)>> = vec![Vec::with_capacity(path.len()); units.len()];
+
+        for (i, scale) in scales.iter().enumerate() {
+            let points: Vec<(f32, f32)> =
+                unit_offsets.iter().map(|o| offset_point(&path, &lengths, i, o.0, o.1 * scale)).collect();
+            let mut taken: HashSet<(usize, usize)> = HashSet::new();
+            let mut blocked: Vec<usize> = Vec::new();
+
+            for (unit, point) in points.iter().enumerate() {
+                if has_clearance(pathing, *point, 0.0) && taken.insert(round_point2(*point)) {
+                    unit_paths[unit].push(*point);
+                } else {
+                    blocked.push(unit);
+                }
+            }
+
+            // Units with blocked or shared slots move to the closest free cell on the same side of walls
+            let component = pathing.component(path[i]);
+            for unit in blocked {
+                let lookup = clamp_cell(pathing, points[unit]);
+                let free = pathing.get_closest_pathable_filtered(lookup, |c| {
+                                      !taken.contains(&c) && pathing.component((c.0 as f32, c.1 as f32)) == component
+                                  });
+
+                match free {
+                    Some(cell) => {
+                        taken.insert(cell);
+                        unit_paths[unit].push((cell.0 as f32, cell.1 as f32));
+                    }
+                    None => unit_paths[unit].push(path[i]),
+                }
+            }
+        }
+
+        unit_paths
+    }
+}
+
+impl Map {
+    /// Pathable slots on arcs around the target, closest to the facing direction first.
+    fn concave_slots(&self,
+                     center: (f32, f32),
+                     target: (f32, f32),
+                     radius: f32,
+                     spacing: f32,
+                     clearance: f32,
+                     count: usize)
+                     -> Vec<(f32, f32)> {
+        let pathing = &self.ground_pathing;
+        let start = pathing.get_closest_pathable(round_point2(center));
+        let facing = if euclidean_distance(center, target) > 0.0 {
+            (center.1 - target.1).atan2(center.0 - target.0)
+        } else {
+            0.0
+        };
+
+        // Slots must be reachable so that walls and chokes split the arc instead of units spreading behind them
+        let limit = euclidean_distance(center, target) + radius + spacing * (count + MAX_ROWS) as f32;
+        let mut reachable: HashSet<(usize, usize)> =
+            pathing.find_destinations_in_inline(start, limit).into_iter().map(|d| d.0).collect();
+        reachable.insert(start);
+
+        let mut slots: Vec<(f32, f32)> = Vec::with_capacity(count);
+
+        for row in 0..count + MAX_ROWS {
+            let row_radius = f32::max(0.0, radius + row as f32 * spacing);
+            // Arc without a radius is just the target
+            let (step, max_steps) = if row_radius > 0.0 {
+                let step = spacing / row_radius;
+                (step, (MAX_ARC_ANGLE / step) as i64)
+            } else {
+                (0.0, 0)
+            };
+
+            // Alternate sides starting from the middle of the arc
+            for i in 0..=max_steps * 2 {
+                let side = if i % 2 == 0 {
+                    i / 2
+                } else {
+                    -(i + 1) / 2
+                };
+                let angle = facing + side as f32 * step;
+                let point = (target.0 + angle.cos() * row_radius, target.1 + angle.sin() * row_radius);
+
+                if slots.iter().any(|s| euclidean_distance(*s, point) < spacing * 0.99)
+                   || !has_clearance(pathing, point, clearance)
+                   || !reachable.contains(&round_point2(point))
+                {
+                    continue;
+                }
+
+                slots.push(point);
+                if slots.len() == count {
+                    return slots;
+                }
+            }
+        }
+
+        slots
+    }
+}
+
+/// Cell of the point moved inside the map
+fn clamp_cell(pathing: &PathFind, point: (f32, f32)) -> (usize, usize) {
+    let x = point.0.round().clamp(0.0, (pathing.width - 1) as f32);
+    let y = point.1.round().clamp(0.0, (pathing.height - 1) as f32);
+    (x as usize, y as usize)
+}
+
+/// Whether all cells within radius of the point are pathable
+fn has_clearance(pathing: &PathFind, point: (f32, f32), radius: f32) -> bool {
+    if point.0 < 0.0 || point.1 < 0.0 {
+        return false;
+    }
+
+    let r = radius.floor() as i64;
+    let (cx, cy) = round_point2(point);
+
+    for x in (cx as i64 - r)..=(cx as i64 + r) {
+        for y in (cy as i64 - r)..=(cy as i64 + r) {
+            if x < 0 || y < 0 || x as usize >= pathing.width || y as usize >= pathing.height {
+                return false;
+            }
+            if pathing.map[x as usize][y as usize] == 0 {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+/// Assigns units to slot indices minimizing the total travel distance
+fn assign_slots(units: &[(f32, f32)], slots: &[(f32, f32)]) -> Vec<Option<usize>> {
+    let mut result = vec![None; units.len()];
+    if slots.is_empty() {
+        return result;
+    }
+
+    let cost = |unit: usize, slot: usize| (euclidean_distance(units[unit], slots[slot]) * ASSIGNMENT_MULT) as i64;
+
+    // Rows can't outnumber columns
+    if units.len() <= slots.len() {
+        let weights = cost_matrix(units.len(), slots.len(), cost);
+        let (_, assignment) = kuhn_munkres_min(&weights);
+        for (unit, slot) in assignment.into_iter().enumerate() {
+            result[unit] = Some(slot);
+        }
+    } else {
+        let weights = cost_matrix(slots.len(), units.len(), |s, u| cost(u, s));
+        let (_, assignment) = kuhn_munkres_min(&weights);
+        for (slot, unit) in assignment.into_iter().enumerate() {
+            result[unit] = Some(slot);
+        }
+    }
+
+    result
+}
+
+fn cost_matrix<F>(rows: usize, columns: usize, cost: F) -> Matrix<i64>
+    where F: Fn(usize, usize) -> i64
+{
+    let mut matrix = Matrix::new(rows, columns, 0);
+    for row in 0..rows {
+        for column in 0..columns {
+            matrix[(row, column)] = cost(row, column);
+        }
+    }
+    matrix
+}
+
+/// How much the formation width is scaled at each waypoint, between 0 and 1.
+/// Free space is limited by the neighbouring waypoints so that the formation compresses before a choke.
+fn formation_scales(pathing: &PathFind, path: &[(f32, f32)], half_width: f32) -> Vec<f32> {
+    if half_width <= 0.0 {
+        return vec![1.0; path.len()];
+    }
+
+    let clearances: Vec<f32> = (0..path.len()).map(|i| lateral_clearance(pathing, path, i, half_width)).collect();
+
+    (0..path.len()).map(|i| {
+                       let start = i.saturating_sub(CLEARANCE_WINDOW);
+                       let end = usize::min(path.len(), i + CLEARANCE_WINDOW + 1);
+                       let clearance = clearances[start..end].iter().cloned().fold(f32::MAX, f32::min);
+                       f32::min(1.0, clearance / half_width)
+                   })
+                   .collect()
+}
+
+/// Offsets as (forward, lateral) from the formation leader, behind the leader is negative forward.
+fn formation_offsets(formation: u8, count: usize, spacing: f32) -> Vec<(f32, f32)> {
+    let mut offsets = Vec::with_capacity(count);
+
+    match formation {
+        FORMATION_BOX => {
+            let columns = (count as f32).sqrt().ceil() as usize;
+            for i in 0..count {
+                let row = i / columns;
+                let row_size = usize::min(columns, count - row * columns);
+                let column = i % columns;
+                offsets.push((-(row as f32) * spacing, (column as f32 - (row_size - 1) as f32 * 0.5) * spacing));
+            }
+        }
+        FORMATION_WEDGE => {
+            let mut row = 0;
+            let mut index = 0;
+            while offsets.len() < count {
+                let row_size = usize::min(row + 1, count - offsets.len());
+                offsets.push((-(row as f32) * spacing, (index as f32 - (row_size - 1) as f32 * 0.5) * spacing));
+                index += 1;
+                if index > row {
+                    row += 1;
+                    index = 0;
+                }
+            }
+        }
+        _ => {
+            for i in 0..count {
+                offsets.push((0.0, (i as f32 - (count - 1) as f32 * 0.5) * spacing));
+            }
+        }
+    }
+
+    offsets
+}
+
+fn cumulative_lengths(path: &[(f32, f32)]) -> Vec<f32> {
+    let mut lengths = Vec::with_capacity(path.len());
+    let mut total = 0.0;
+    lengths.push(0.0);
+
+    for segment in path.windows(2) {
+        total += euclidean_distance(segment[0], segment[1]);
+        lengths.push(total);
+    }
+
+    lengths
+}
+
+/// Unit vector along the path at the waypoint
+fn path_direction(path: &[(f32, f32)], index: usize) -> (f32, f32) {
+    let previous = path[index.saturating_sub(1)];
+    let next = path[usize::min(path.len() - 1, index + 1)];
+    let dx = next.0 - previous.0;
+    let dy = next.1 - previous.1;
+    let length = (dx * dx + dy * dy).sqrt();
+
+    if length > 0.0 {
+        (dx / length, dy / length)
+    } else {
+        (1.0, 0.0)
+    }
+}
+
+/// Point forward along the path from the waypoint and lateral to the left of the path direction
+fn offset_point(path: &[(f32, f32)], lengths: &[f32], index: usize, forward: f32, lateral: f32) -> (f32, f32) {
+    let target_length = lengths[index] + forward;
+    let mut i = index;
+    while i > 0 && lengths[i] > target_length {
+        i -= 1;
+    }
+
+    let direction = path_direction(path, i);
+    let base = if target_length < 0.0 {
+        // Behind the start of the path
+        (path[0].0 + direction.0 * target_length, path[0].1 + direction.1 * target_length)
+    } else if i + 1 < path.len() && lengths[i + 1] > lengths[i] {
+        let t = f32::min(1.0, (target_length - lengths[i]) / (lengths[i + 1] - lengths[i]));
+        (path[i].0 + (path[i + 1].0 - path[i].0) * t, path[i].1 + (path[i + 1].1 - path[i].1) * t)
+    } else {
+        path[i]
+    };
+
+    (base.0 - direction.1 * lateral, base.1 + direction.0 * lateral)
+}
+
+/// Free distance to the closest blocked cell on either side of the waypoint, up to max_distance
+fn lateral_clearance(pathing: &PathFind, path: &[(f32, f32)], index: usize, max_distance: f32) -> f32 {
+    let direction = path_direction(path, index);
+    let normal = (-direction.1, direction.0);
+    let point = path[index];
+    let mut clearance = max_distance;
+
+    for side in [-1.0, 1.0] {
+        let mut distance = CLEARANCE_STEP;
+        while distance <= max_distance {
+            let cell = (point.0 + normal.0 * distance * side, point.1 + normal.1 * distance * side);
+            if !has_clearance(pathing, cell, 0.0) {
+                clearance = f32::min(clearance, distance - CLEARANCE_STEP);
+                break;
+            }
+            distance += CLEARANCE_STEP;
+        }
+    }
+
+    clearance
+}
@@ -0,0 +1,166 @@
+use pyo3::prelude::*;
+
+use crate::path_find::rectangle::Rectangle;
+use crate::path_find::shapes::InfluenceShape;
+
+use super::influence::MAPS_BOTH;
+use super::map::Map;
+
+/// Short-lived influence, like Psionic Storm or Corrosive Bile.
+#[derive(Clone)]
+pub struct Hazard {
+    pub shape: InfluenceShape,
+    pub value: f32,
+    pub fade: f32,
+    pub map_type: usize,
+    pub expires_at: u32,
+}
+
+/// Short-lived ground block, like a Force Field, that colossi walk over.
+#[derive(Clone)]
+pub struct TimedBlock {
+    pub center: (f32, f32),
+    pub size: (usize, usize),
+    pub expires_at: u32,
+}
+
+#[pymethods]
+impl Map {
+    /// Adds influence that stays on the maps through `reset` until `advance` reaches the expiry game loop.
+    /// map_type uses the same values as `add_influence_fading` and defaults to both ground and air.
+    pub fn add_hazard(&mut self,
+                      shape: InfluenceShape,
+                      value: f32,
+                      expires_at_loop: u32,
+                      map_type: Option<usize>,
+                      fade: Option<f32>) {
+        if expires_at_loop <= self.game_loop {
+            return;
+        }
+
+        let hazard = Hazard { shape,
+                              value,
+                              fade: fade.unwrap_or(0.0),
+                              map_type: map_type.unwrap_or(MAPS_BOTH),
+                              expires_at: expires_at_loop };
+        self.apply_hazard(&hazard);
+        self.hazards.push(hazard);
+    }
+
+    /// Blocks ground and reaper pathing until `advance` reaches the expiry game loop, colossus pathing isn't blocked.
+    pub fn add_timed_block(&mut self, center: (f32, f32), size: (usize, usize), expires_at_loop: u32) {
+        if expires_at_loop <= self.game_loop {
+            return;
+        }
+
+        self.block_timed_maps(center, size);
+        self.timed_blocks.push(TimedBlock { center,
+                                            size,
+                                            expires_at: expires_at_loop });
+    }
+
+    /// Removes hazards and timed blocks that have expired by the game loop and decays influence layers.
+    pub fn advance(&mut self, game_loop: u32) {
+        self.game_loop = game_loop;
+        self.decay_all_layers(game_loop);
+
+        let (expired, active): (Vec<Hazard>, Vec<Hazard>) =
+            self.hazards.drain(..).partition(|h| h.expires_at <= game_loop);
+        self.hazards = active;
+
+        for hazard in &expired {
+            for mapping in self.get_influence_maps(hazard.map_type) {
+                hazard.shape.remove(&mut mapping.map, hazard.value, hazard.fade);
+            }
+        }
+
+        let (expired, active): (Vec<TimedBlock>, Vec<TimedBlock>) =
+            self.timed_blocks.drain(..).partition(|b| b.expires_at <= game_loop);
+        self.timed_blocks = active;
+
+        for block in &expired {
+            self.mark_zone_distances_changed(block.center, block.size);
+            self.restore_timed_block(block);
+        }
+    }
+
+    /// Hazards that haven't expired yet as (expires at loop, value)
+    #[getter(hazards)]
+    fn get_hazards(&self) -> Vec<(u32, f32)> { self.hazards.iter().map(|h| (h.expires_at, h.value)).collect() }
+
+    /// Timed blocks that haven't expired yet as (center, expires at loop)
+    #[getter(timed_blocks)]
+    fn get_timed_blocks(&self) -> Vec<((f32, f32), u32)> {
+        self.timed_blocks.iter().map(|b| (b.center, b.expires_at)).collect()
+    }
+}
+
+impl Map {
+    fn apply_hazard(&mut self, hazard: &Hazard) {
+        for mapping in self.get_influence_maps(hazard.map_type) {
+            hazard.shape.apply(&mut mapping.map, hazard.value, hazard.fade);
+        }
+    }
+
+    /// Blocks the cell rectangle on the maps that timed blocks affect
+    fn block_timed_maps(&mut self, center: (f32, f32), size: (usize, usize)) {
+        self.mark_zone_distances_changed(center, size);
+        self.ground_pathing.create_block(center, size);
+        self.reaper_pathing.create_block(center, size);
+    }
+
+    fn apply_timed_blocks(&mut self) {
+        for block in self.timed_blocks.clone() {
+            self.block_timed_maps(block.center, block.size);
+        }
+    }
+
+    /// Blocks active timed blocks again where they overlap the rectangles that have been opened
+    pub(crate) fn apply_timed_blocks_within(&mut self, rects: &[Rectangle]) {
+        let width = self.ground_pathing.width;
+        let height = self.ground_pathing.height;
+
+        for block in self.timed_blocks.clone() {
+            let rect = Rectangle::init_from_center(block.center, block.size, width, height);
+            if rects.iter().any(|r| r.overlaps(&rect)) {
+                self.block_timed_maps(block.center, block.size);
+            }
+        }
+    }
+
+    /// Opens cells of an expired block that no other block covers and puts back
+    /// normal influence, active hazards and decay layers on them.
+    /// Other influence added since the last reset is lost in those cells.
+    fn restore_timed_block(&mut self, block: &TimedBlock) {
+        let width = self.ground_pathing.width;
+        let height = self.ground_pathing.height;
+        let rect = Rectangle::init_from_center(block.center, block.size, width, height);
+        let covering: Vec<Rectangle> =
+            self.blocks
+                .iter()
+                .cloned()
+                .chain(self.timed_blocks.iter().map(|b| (b.center, b.size)))
+                .map(|(center, size)| Rectangle::init_from_center(center, size, width, height))
+                .filter(|r| r.overlaps(&rect))
+                .collect();
+
+        self.ground_pathing.restore_block_rust(block.center, block.size, &covering);
+        self.reaper_pathing.restore_block_rust(block.center, block.size, &covering);
+
+        for hazard in self.hazards.clone() {
+            for mapping in self.get_timed_block_influence_maps(hazard.map_type) {
+                hazard.shape.apply_within(&mut mapping.map, hazard.value, hazard.fade, &rect);
+            }
+        }
+        self.apply_decay_layers_within(&rect);
+    }
+
+    /// Applies active timed blocks and hazards again after the maps have been reset.
+    pub fn apply_hazards(&mut self) {
+        self.apply_timed_blocks();
+
+        for hazard in self.hazards.clone() {
+            self.apply_hazard(&hazard);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::path_find::pos;
+use crate::path_find::shapes::InfluenceShape;
 use crate::path_find::{octile_distance, rectangle, PathFind};
 use pyo3::prelude::*;
 
@@ -8,10 +9,10 @@ const DIFFERENCE: usize = 16;
 const Y_MULT: usize = 1000000;
 use crate::mapping::map::Map;
 
-const MAPS_PURE_GROUND: usize = 0;
-const MAPS_GROUND: usize = 1;
-const MAPS_AIR: usize = 2;
-const MAPS_BOTH: usize = 3;
+pub const MAPS_PURE_GROUND: usize = 0;
+pub const MAPS_GROUND: usize = 1;
+pub const MAPS_AIR: usize = 2;
+pub const MAPS_BOTH: usize = 3;
 
 #[pymethods]
 impl Map {
@@ -86,50 +87,60 @@ impl Map {
                                 influence: f32,
                                 min: f32,
                                 max: f32) {
-        let mult = 1.0 / pos::MULTF32;
-        let mult2 = 1.0 / (max - min);
-        let value = influence as usize;
-        let mult_min = min * pos::MULTF32;
-        let mult_max = max * pos::MULTF32;
-        let mut maps: Vec<&mut PathFind>;
+        let mut maps = self.get_influence_maps(map_type);
 
-        if map_type == MAPS_PURE_GROUND {
-            maps = self.get_pure_ground_influence_maps();
-        } else if map_type == MAPS_GROUND {
-            maps = self.get_ground_influence_maps();
-        } else if map_type == MAPS_AIR {
-            maps = self.get_air_influence_maps();
-        } else {
-            maps = self.get_both_influence_maps();
+        for position_f in &positions {
+            let position = (position_f.0.round() as usize, position_f.1.round() as usize);
+            add_fading_influence(&mut maps, position, influence, 0.0, min, max);
         }
+    }
 
-        let diameter = ((max * 2f32) as usize) + 2;
-        let rect_size = (diameter, diameter);
+    /// Adds influence inside the shape, fading out over fade distance outside of it.
+    pub fn add_influence_shape(&mut self, map_type: usize, shape: &InfluenceShape, influence: f32, fade: Option<f32>) {
+        let fade = fade.unwrap_or(0.0);
 
-        for position_f in &positions {
-            let position = (position_f.0.round() as usize, position_f.1.round() as usize);
-            let rect = rectangle::Rectangle::init_from_center2(position, rect_size, maps[0].width, maps[0].height);
+        for mapping in self.get_influence_maps(map_type) {
+            shape.apply(&mut mapping.map, influence, fade);
+        }
+    }
+}
 
-            for x in rect.x..rect.x_end {
-                for y in rect.y..rect.y_end {
-                    let d = octile_distance(position, (x, y)) as f32;
-                    if d < mult_max {
-                        if d < mult_min {
-                            for mapping in maps.iter_mut() {
-                                let old_val = mapping.map[x][y];
-                                if old_val > 0 {
-                                    mapping.map[x][y] = old_val + value;
-                                }
-                            }
-                        } else {
-                            // Fading threshold
-                            let value_fading = (influence * (1.0 - (d * mult - min) * mult2)) as usize;
-                            for mapping in maps.iter_mut() {
-                                let old_val = mapping.map[x][y];
-                                if old_val > 0 && value_fading > 0 {
-                                    mapping.map[x][y] = old_val + value_fading;
-                                }
-                            }
+/// Adds flat influence between hollow and min distances, fading out from min to max distance.
+pub fn add_fading_influence(maps: &mut [&mut PathFind],
+                            position: (usize, usize),
+                            influence: f32,
+                            hollow: f32,
+                            min: f32,
+                            max: f32) {
+    let mult = 1.0 / pos::MULTF32;
+    let mult2 = 1.0 / (max - min);
+    let value = influence as usize;
+    let mult_hollow = hollow * pos::MULTF32;
+    let mult_min = min * pos::MULTF32;
+    let mult_max = max * pos::MULTF32;
+
+    let diameter = ((max * 2f32) as usize) + 2;
+    let rect_size = (diameter, diameter);
+    let rect = rectangle::Rectangle::init_from_center2(position, rect_size, maps[0].width, maps[0].height);
+
+    for x in rect.x..rect.x_end {
+        for y in rect.y..rect.y_end {
+            let d = octile_distance(position, (x, y)) as f32;
+            if d < mult_max && d >= mult_hollow {
+                if d < mult_min {
+                    for mapping in maps.iter_mut() {
+                        let old_val = mapping.map[x][y];
+                        if old_val > 0 {
+                            mapping.map[x][y] = old_val + value;
+                        }
+                    }
+                } else {
+                    // Fading threshold
+                    let value_fading = (influence * (1.0 - (d * mult - min) * mult2)) as usize;
+                    for mapping in maps.iter_mut() {
+                        let old_val = mapping.map[x][y];
+                        if old_val > 0 && value_fading > 0 {
+                            mapping.map[x][y] = old_val + value_fading;
                         }
                     }
                 }
@@ -139,6 +150,27 @@ impl Map {
 }
 
 impl Map {
+    pub fn get_influence_maps(&mut self, map_type: usize) -> Vec<&mut PathFind> {
+        if map_type == MAPS_PURE_GROUND {
+            self.get_pure_ground_influence_maps()
+        } else if map_type == MAPS_GROUND {
+            self.get_ground_influence_maps()
+        } else if map_type == MAPS_AIR {
+            self.get_air_influence_maps()
+        } else {
+            self.get_both_influence_maps()
+        }
+    }
+
+    /// Influence maps of the type that timed blocks affect, colossi and air units pass over them
+    pub(crate) fn get_timed_block_influence_maps(&mut self, map_type: usize) -> Vec<&mut PathFind> {
+        if map_type == MAPS_AIR {
+            Vec::new()
+        } else {
+            self.get_pure_ground_influence_maps()
+        }
+    }
+
     fn get_both_influence_maps(&mut self) -> Vec<&mut PathFind> {
         let mut maps = Vec::<&mut PathFind>::new();
         maps.push(&mut self.ground_pathing);
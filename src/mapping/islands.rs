@@ -0,0 +1,129 @@
+use std::collections::{HashMap, HashSet};
+
+use pyo3::prelude::*;
+
+use crate::helpers::round_point2;
+use crate::path_find::euclidean_distance;
+
+use super::distance_fields::DistanceField;
+use super::map::Map;
+
+// Smaller areas are usually leftovers of the pathing grid rather than places to build or land
+const MIN_ISLAND_AREA: usize = 9;
+const DROP_POINT_COUNT: usize = 3;
+// Drop points closer than this to each other are considered the same spot
+const DROP_POINT_SPACING: f32 = 4.0;
+const MAP_TYPE_AIR: u8 = 3;
+
+/// Ground pathable area that can't be reached by ground from the start locations
+#[pyclass]
+#[derive(Clone)]
+pub struct Island {
+    pub area: usize,
+    pub centroid: (f32, f32),
+    pub resources: Vec<(f32, f32)>,
+    pub drop_points: Vec<((usize, usize), f32)>,
+}
+
+#[pymethods]
+impl Island {
+    /// Amount of ground pathable grid cells in the island
+    #[getter(area)]
+    fn get_area(&self) -> usize { self.area }
+
+    /// Average position of the cells, can be outside the island when it isn't convex
+    #[getter(centroid)]
+    fn get_centroid(&self) -> (f32, f32) { self.centroid }
+
+    /// Given resources that are next to the island
+    #[getter(resources)]
+    fn get_resources(&self) -> Vec<(f32, f32)> { self.resources.clone() }
+
+    /// Cells in the island closest to the main ground area by air, as (cell, air distance)
+    #[getter(drop_points)]
+    fn get_drop_points(&self) -> Vec<((usize, usize), f32)> { self.drop_points.clone() }
+}
+
+#[pymethods]
+impl Map {
+    /// Finds ground pathable areas that can't be reached by ground from any of the start locations,
+    /// such as islands, pockets and expansions behind cliffs. Blocks are ignored.
+    /// Resources are assigned to the island next to them. Largest islands come first.
+    pub fn islands(&self,
+                   start_locations: Vec<(f32, f32)>,
+                   resources: Option<Vec<(f32, f32)>>,
+                   min_area: Option<usize>)
+                   -> Vec<Island> {
+        let ground = &self.ground_pathing;
+        let min_area = min_area.unwrap_or(MIN_ISLAND_AREA);
+        let main_components: HashSet<u32> =
+            start_locations.iter()
+                           .map(|s| ground.terrain_component(ground.get_closest_pathable(round_point2(*s))))
+                           .filter(|c| *c != 0)
+                           .collect();
+
+        let mut main_cells: Vec<(usize, usize)> = Vec::new();
+        let mut areas: HashMap<u32, Vec<(usize, usize)>> = HashMap::new();
+
+        for x in 0..ground.width {
+            for y in 0..ground.height {
+                let component = ground.terrain_component((x, y));
+                if component == 0 {
+                    continue;
+                }
+
+                if main_components.contains(&component) {
+                    main_cells.push((x, y));
+                } else {
+                    areas.entry(component).or_default().push((x, y));
+                }
+            }
+        }
+
+        areas.retain(|_, cells| cells.len() >= min_area);
+        if areas.is_empty() {
+            return Vec::new();
+        }
+
+        let air_distances = DistanceField::new(&self.air_pathing.map, &main_cells, MAP_TYPE_AIR);
+        let mut islands: HashMap<u32, Island> =
+            areas.iter().map(|(component, cells)| (*component, new_island(cells, &air_distances))).collect();
+
+        for resource in resources.unwrap_or_default() {
+            let cell = ground.get_closest_pathable(round_point2(resource));
+            if let Some(island) = islands.get_mut(&ground.terrain_component(cell)) {
+                island.resources.push(resource);
+            }
+        }
+
+        let mut result: Vec<Island> = islands.into_values().collect();
+        result.sort_by(|a, b| b.area.cmp(&a.area).then(a.centroid.0.total_cmp(&b.centroid.0)));
+        result
+    }
+}
+
+fn new_island(cells: &[(usize, usize)], air_distances: &DistanceField) -> Island {
+    let count = cells.len() as f32;
+    let centroid =
+        (cells.iter().map(|c| c.0 as f32).sum::<f32>() / count, cells.iter().map(|c| c.1 as f32).sum::<f32>() / count);
+
+    let mut candidates: Vec<((usize, usize), f32)> =
+        cells.iter().filter_map(|c| air_distances.distance(*c).map(|d| (*c, d))).collect();
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let mut drop_points: Vec<((usize, usize), f32)> = Vec::new();
+    for (cell, distance) in candidates {
+        let point = (cell.0 as f32, cell.1 as f32);
+        if drop_points.iter().all(|(p, _)| euclidean_distance((p.0 as f32, p.1 as f32), point) >= DROP_POINT_SPACING) {
+            drop_points.push((cell, distance));
+            if drop_points.len() == DROP_POINT_COUNT {
+                break;
+            }
+        }
+    }
+
+    Island { area: cells.len(),
+             centroid,
+             resources: Vec::new(),
+             drop_points }
+}
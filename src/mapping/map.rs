@@ -1,11 +1,22 @@
-use crate::{helpers::round_point2, path_find::PathFind};
+use crate::{
+    helpers::round_point2,
+    path_find::{exposure::PathExposure, octile_distance, octile_distance_f32, rectangle, PathFind},
+};
 use pyo3::prelude::*;
 
 // extern crate test;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use super::chokes::{group_chokes, Choke};
+use super::decay::DecayLayer;
+use super::distance_fields::DistanceField;
+use super::hazards::{Hazard, TimedBlock};
+use super::overlord_spots::{
+    score_overlord_spots, set_ground_distances, OverlordSpot, ANTI_AIR_RANGE, OVERLORD_SIGHT_RANGE,
+};
 use super::vision::{VisionMap, VisionUnit};
+use super::watchtowers::Watchtower;
+use super::zone_distances::ZoneDistances;
 use crate::mapping::chokes::solve_chokes;
 use crate::mapping::climb::modify_climb;
 use crate::mapping::map_point;
@@ -22,13 +33,24 @@ pub struct Map {
     pub colossus_pathing: PathFind,
     pub reaper_pathing: PathFind,
     pub points: Vec<Vec<map_point::MapPoint>>,
-    pub overlord_spots: Vec<(f32, f32)>,
+    pub overlord_spots: Vec<OverlordSpot>,
     #[pyo3(get, set)]
     pub influence_colossus_map: bool,
     #[pyo3(get, set)]
     pub influence_reaper_map: bool,
     pub chokes: Vec<Choke>,
     pub vision_map: VisionMap,
+    pub enemy_vision_map: VisionMap,
+    pub watchtowers: Vec<Watchtower>,
+    pub hazards: Vec<Hazard>,
+    pub timed_blocks: Vec<TimedBlock>,
+    /// Blocks created since the last reset as (center, size), timed blocks excluded
+    pub blocks: Vec<((f32, f32), (usize, usize))>,
+    pub game_loop: u32,
+    pub decay_layers: HashMap<String, DecayLayer>,
+    pub distance_fields: HashMap<String, DistanceField>,
+    pub zone_centers: Vec<(usize, usize)>,
+    pub zone_distances: HashMap<u8, ZoneDistances>,
 }
 
 #[pymethods]
@@ -63,7 +85,7 @@ impl Map {
     fn get_vision_map(&self) -> Vec<Vec<usize>> { self.vision_map.draw_vision() }
 
     #[getter(overlord_spots)]
-    fn get_overlord_spots(&self) -> Vec<(f32, f32)> { self.overlord_spots.clone() }
+    fn get_overlord_spots(&self) -> Vec<(f32, f32)> { self.overlord_spots.iter().map(|s| s.position).collect() }
 
     #[getter(chokes)]
     pub fn get_chokes(&self) -> Vec<Choke> { self.chokes.clone() }
@@ -122,28 +144,49 @@ impl Map {
 
     /// Reset all mapping to their originals.
     pub fn reset(&mut self) {
+        for (center, size) in std::mem::take(&mut self.blocks) {
+            self.mark_zone_distances_changed(center, size);
+        }
+        for block in self.timed_blocks.clone() {
+            self.mark_zone_distances_changed(block.center, block.size);
+        }
         self.ground_pathing.reset_void();
         self.air_pathing.reset_void();
         self.colossus_pathing.reset_void();
         self.reaper_pathing.reset_void();
+        self.apply_hazards();
+        self.apply_decay_layers();
     }
 
     pub fn create_block(&mut self, center: (f32, f32), size: (usize, usize)) {
-        self.ground_pathing.create_block(center, size);
-        self.colossus_pathing.create_block(center, size);
-        self.reaper_pathing.create_block(center, size);
+        self.blocks.push((center, size));
+        self.block_ground_maps(center, size);
     }
 
     pub fn create_blocks(&mut self, centers: Vec<(f32, f32)>, size: (usize, usize)) {
+        self.blocks.extend(centers.iter().map(|c| (*c, size)));
+        for center in &centers {
+            self.mark_zone_distances_changed(*center, size);
+        }
         self.ground_pathing.create_blocks_rust(&centers, size);
         self.colossus_pathing.create_blocks_rust(&centers, size);
         self.reaper_pathing.create_blocks_rust(&centers, size);
     }
 
     pub fn remove_blocks(&mut self, centers: Vec<(f32, f32)>, size: (usize, usize)) {
+        self.blocks.retain(|b| b.1 != size || !centers.contains(&b.0));
+        for center in &centers {
+            self.mark_zone_distances_changed(*center, size);
+        }
         self.ground_pathing.remove_blocks_rust(&centers, size);
         self.colossus_pathing.remove_blocks_rust(&centers, size);
         self.reaper_pathing.remove_blocks_rust(&centers, size);
+
+        let width = self.ground_pathing.width;
+        let height = self.ground_pathing.height;
+        let rects: Vec<rectangle::Rectangle> =
+            centers.iter().map(|c| rectangle::Rectangle::init_from_center(*c, size, width, height)).collect();
+        self.apply_timed_blocks_within(&rects);
     }
 
     pub fn get_borders(&self) -> Vec<(usize, usize)> {
@@ -239,6 +282,68 @@ impl Map {
         map.find_path_basic(start_int, end_int, possible_heuristic)
     }
 
+    /// Finds the shortest path that never enters a cell with influence above max_influence
+    /// and keeps the total exposure under the exposure budget. Returns None when no such path exists.
+    pub fn find_path_constrained(&self,
+                                 map_type: u8,
+                                 start: (f32, f32),
+                                 end: (f32, f32),
+                                 max_influence: Option<usize>,
+                                 exposure_budget: Option<f32>)
+                                 -> Option<(Vec<(usize, usize)>, f32)> {
+        let map = self.get_map(map_type);
+        map.find_path_constrained(round_point2(start), round_point2(end), max_influence, exposure_budget)
+    }
+
+    /// Whether a path exists between the points, answered from connected component labels.
+    pub fn is_reachable(&self, map_type: u8, start: (f32, f32), end: (f32, f32)) -> bool {
+        let map = self.get_map(map_type);
+        map.is_reachable(start, end)
+    }
+
+    /// Finds non-colliding paths for a batch of units with windowed cooperative A*, one cell per time step.
+    pub fn find_paths_cooperative(&self,
+                                  map_type: u8,
+                                  starts: Vec<(f32, f32)>,
+                                  goals: Vec<(f32, f32)>,
+                                  window: Option<usize>,
+                                  radii: Option<Vec<f32>>)
+                                  -> PyResult<Vec<Vec<(usize, usize)>>> {
+        let map = self.get_map(map_type);
+        let starts = starts.into_iter().map(round_point2).collect();
+        let goals = goals.into_iter().map(round_point2).collect();
+        map.find_paths_cooperative(starts, goals, window, radii)
+    }
+
+    /// Finds a path within walking distance that gets away from the threats while avoiding influence and corners.
+    pub fn find_flee_path(&self,
+                          map_type: u8,
+                          start: (f32, f32),
+                          threats: Vec<(f32, f32)>,
+                          distance: f32)
+                          -> (Vec<(usize, usize)>, f32) {
+        let map = self.get_map(map_type);
+        map.find_flee_path(start, threats, distance)
+    }
+
+    /// Unit vector pointing towards decreasing influence over pathable cells within radius, (0, 0) when flat.
+    pub fn safe_direction(&self, map_type: u8, position: (f32, f32), radius: f32) -> (f32, f32) {
+        let map = self.get_map(map_type);
+        map.influence_gradient(position, radius)
+    }
+
+    /// Integrates influence along the path of cells or waypoints for a unit moving at unit_speed.
+    /// Threshold is influence above normal influence and defaults to 0. Unit speed must be above 0.
+    pub fn path_exposure(&self,
+                         map_type: u8,
+                         path: Vec<(f32, f32)>,
+                         unit_speed: f32,
+                         threshold: Option<usize>)
+                         -> PyResult<PathExposure> {
+        let map = self.get_map(map_type);
+        map.path_exposure(path, unit_speed, threshold)
+    }
+
     /// Finds a compromise where low influence matches with close position to the start position.
     fn find_low_inside_walk(&self,
                             map_type: u8,
@@ -251,11 +356,27 @@ impl Map {
     }
 
     // Vision map calls
-    pub fn clear_vision(&mut self) { self.vision_map.clear(); }
+    pub fn clear_vision(&mut self) {
+        self.vision_map.clear();
+        self.apply_watchtowers();
+    }
     pub fn add_vision_unit(&mut self, unit: VisionUnit) { self.vision_map.add_unit(unit); }
     pub fn calculate_vision_map(&mut self) { self.vision_map.calculate_vision_map(&self.points); }
     pub fn vision_status(&self, point: (f32, f32)) -> usize { self.vision_map.vision_status(point) }
 
+    /// Adds or replaces a vision unit by id and updates only the cells it affects.
+    pub fn set_vision_unit(&mut self, unit_id: u64, unit: VisionUnit) {
+        self.vision_map.set_unit(unit_id, unit, &self.points);
+    }
+
+    /// Moves a vision unit added with `set_vision_unit`. Returns false if the unit wasn't found.
+    pub fn move_vision_unit(&mut self, unit_id: u64, position: (f32, f32)) -> bool {
+        self.vision_map.move_unit(unit_id, position, &self.points)
+    }
+
+    /// Removes a vision unit added with `set_vision_unit`. Returns false if the unit wasn't found.
+    pub fn remove_vision_unit(&mut self, unit_id: u64) -> bool { self.vision_map.remove_unit(unit_id) }
+
     pub fn add_influence_to_vision(&mut self, map_type: u8, seen_value: usize, detection_value: usize) {
         let vision_map = &self.vision_map; // self.get_vision();
         let map = {
@@ -274,6 +395,113 @@ impl Map {
 
         map.add_influence_to_map_by_vision(&vision_map, seen_value, detection_value);
     }
+
+    // Enemy vision map calls, use estimated enemy units
+    pub fn clear_enemy_vision(&mut self) { self.enemy_vision_map.clear(); }
+    pub fn add_enemy_vision_unit(&mut self, unit: VisionUnit) { self.enemy_vision_map.add_unit(unit); }
+    pub fn calculate_enemy_vision_map(&mut self) { self.enemy_vision_map.calculate_vision_map(&self.points); }
+    pub fn enemy_vision_status(&self, point: (f32, f32)) -> usize { self.enemy_vision_map.vision_status(point) }
+
+    #[getter(enemy_vision_map)]
+    fn get_enemy_vision_map(&self) -> Vec<Vec<usize>> { self.enemy_vision_map.draw_vision() }
+
+    pub fn set_enemy_vision_unit(&mut self, unit_id: u64, unit: VisionUnit) {
+        self.enemy_vision_map.set_unit(unit_id, unit, &self.points);
+    }
+
+    pub fn move_enemy_vision_unit(&mut self, unit_id: u64, position: (f32, f32)) -> bool {
+        self.enemy_vision_map.move_unit(unit_id, position, &self.points)
+    }
+
+    pub fn remove_enemy_vision_unit(&mut self, unit_id: u64) -> bool { self.enemy_vision_map.remove_unit(unit_id) }
+
+    /// Finds a path that avoids enemy vision. Cells only seen by the enemy cost seen_cost extra and
+    /// detected cells cost detection_cost extra whether they are seen or not, on top of current influence.
+    /// Use low seen_cost and high detection_cost for cloaked units.
+    pub fn find_path_stealth(&self,
+                             map_type: u8,
+                             start: (f32, f32),
+                             end: (f32, f32),
+                             seen_cost: usize,
+                             detection_cost: usize,
+                             possible_heuristic: Option<u8>)
+                             -> (Vec<(usize, usize)>, f32) {
+        let map = self.get_map(map_type);
+        map.find_path_unseen(&self.enemy_vision_map,
+                             round_point2(start),
+                             round_point2(end),
+                             seen_cost,
+                             detection_cost,
+                             possible_heuristic)
+    }
+
+    // Vision memory calls
+    pub fn update_vision_memory(&mut self, game_loop: u32) { self.vision_map.update_memory(game_loop); }
+
+    /// Game loops since the position was last seen or detected.
+    pub fn vision_staleness(&self, point: (f32, f32), game_loop: u32) -> u32 {
+        self.vision_map.staleness(point, game_loop)
+    }
+
+    /// Returns all pathable cells that haven't been seen or detected for at least the given amount of game loops.
+    pub fn cells_not_seen_for(&self, game_loop: u32, loops: u32) -> Vec<(usize, usize)> {
+        let mut result = Vec::<(usize, usize)>::new();
+
+        for x in 0..self.air_pathing.width {
+            for y in 0..self.air_pathing.height {
+                if self.points[x][y].pathable && game_loop.saturating_sub(self.vision_map.last_seen[x][y]) >= loops {
+                    result.push((x, y));
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Returns pathable cells within the distance from center that were seen the longest time ago,
+    /// oldest first, together with the game loop they were last seen on.
+    pub fn oldest_seen_cells(&self, center: (f32, f32), distance: f32, count: usize) -> Vec<((usize, usize), u32)> {
+        let center_int = round_point2(center);
+        let diameter = ((distance * 2f32) as usize) + 2;
+        let rect = rectangle::Rectangle::init_from_center2(center_int,
+                                                           (diameter, diameter),
+                                                           self.air_pathing.width,
+                                                           self.air_pathing.height);
+        let mut result = Vec::<((usize, usize), u32)>::new();
+
+        for x in rect.x..rect.x_end {
+            for y in rect.y..rect.y_end {
+                if self.points[x][y].pathable && octile_distance_f32(center_int, (x, y)) <= distance {
+                    result.push(((x, y), self.vision_map.last_seen[x][y]));
+                }
+            }
+        }
+
+        result.sort_by_key(|(pos, last_seen)| (*last_seen, octile_distance(center_int, *pos)));
+        result.truncate(count);
+        result
+    }
+
+    /// Adds influence to cells based on how long ago they were seen,
+    /// the influence reaches max value after full_staleness game loops.
+    pub fn add_influence_by_staleness(&mut self, map_type: u8, game_loop: u32, max_value: f32, full_staleness: u32) {
+        let vision_map = &self.vision_map;
+        let map = {
+            if map_type == 0 {
+                &mut self.ground_pathing
+            } else if map_type == 1 {
+                &mut self.reaper_pathing
+            } else if map_type == 2 {
+                &mut self.colossus_pathing
+            } else if map_type == 3 {
+                &mut self.air_pathing
+            } else {
+                panic!("Map type {} does not exist", map_type)
+            }
+        };
+
+        map.add_influence_to_map_by_memory(vision_map, game_loop, max_value, full_staleness);
+    }
 }
 
 impl Map {
@@ -296,7 +524,7 @@ impl Map {
         let mut border_map = vec![vec![0; height]; width];
         let mut fly_map = vec![vec![0; height]; width];
         let mut reaper_map = vec![vec![0; height]; width];
-        let mut overlord_spots: Vec<(f32, f32)> = Vec::new();
+        let mut overlord_spots: Vec<OverlordSpot> = Vec::new();
 
         let mut choke_lines = Vec::<((usize, usize), (usize, usize))>::new();
         let x_left_border = x_start - 1;
@@ -398,15 +626,19 @@ impl Map {
                     if flood_fill_overlord(&mut points, x, y, target_height, true, &mut set) {
                         let mut spot = (0_f32, 0_f32);
                         let count = set.len();
+                        let mut size = 0;
                         for value in set {
                             set_handled_overlord_spots.insert(value);
-                            let cx = (value % Y_MULT) as f32;
-                            let cy = (value / Y_MULT) as f32;
-                            spot = (spot.0 + cx, spot.1 + cy);
+                            let cx = value % Y_MULT;
+                            let cy = value / Y_MULT;
+                            if points[cx][cy].height == target_height {
+                                size += 1;
+                            }
+                            spot = (spot.0 + cx as f32, spot.1 + cy as f32);
                         }
 
                         spot = (spot.0 / count as f32, spot.1 / count as f32);
-                        overlord_spots.push(spot);
+                        overlord_spots.push(OverlordSpot::new(spot, size, target_height));
                     } else {
                         set.clear();
                         flood_fill_overlord(&mut points, x, y, target_height, false, &mut set);
@@ -424,10 +656,13 @@ impl Map {
         let colossus_pathing = PathFind::new_internal(reaper_map.clone());
         let reaper_pathing = PathFind::new_internal(reaper_map);
         let vision_map = VisionMap::new_internal(width, height);
+        let enemy_vision_map = VisionMap::new_internal(width, height);
 
         let influence_colossus_map = false;
         let influence_reaper_map = false;
         let chokes = group_chokes(&mut choke_lines, &mut points);
+        set_ground_distances(&mut overlord_spots, &points);
+        score_overlord_spots(&mut overlord_spots, &points, &chokes, &[], OVERLORD_SIGHT_RANGE, ANTI_AIR_RANGE);
 
         Map { ground_pathing,
               air_pathing,
@@ -438,10 +673,28 @@ impl Map {
               influence_colossus_map,
               influence_reaper_map,
               chokes,
-              vision_map }
+              vision_map,
+              enemy_vision_map,
+              watchtowers: Vec::new(),
+              hazards: Vec::new(),
+              timed_blocks: Vec::new(),
+              blocks: Vec::new(),
+              game_loop: 0,
+              decay_layers: HashMap::new(),
+              distance_fields: HashMap::new(),
+              zone_centers: Vec::new(),
+              zone_distances: HashMap::new() }
+    }
+
+    /// Blocks the cell rectangle on all ground pathing maps
+    pub(crate) fn block_ground_maps(&mut self, center: (f32, f32), size: (usize, usize)) {
+        self.mark_zone_distances_changed(center, size);
+        self.ground_pathing.create_block(center, size);
+        self.colossus_pathing.create_block(center, size);
+        self.reaper_pathing.create_block(center, size);
     }
 
-    fn get_map(&self, map_type: u8) -> &PathFind {
+    pub(crate) fn get_map(&self, map_type: u8) -> &PathFind {
         if map_type == 0 {
             return &self.ground_pathing;
         }
@@ -23,6 +23,7 @@ pub struct MapPoint {
     pub overlord_spot: bool,
     pub is_border: bool,
     pub is_choke: bool,
+    pub los_blocker: bool,
 }
 
 impl MapPoint {
@@ -38,6 +39,7 @@ impl MapPoint {
         let overlord_spot = false;
         let is_border = false;
         let is_choke = false;
+        let los_blocker = false;
 
         MapPoint { zone_index,
                    cliff_type,
@@ -49,6 +51,7 @@ impl MapPoint {
                    height,
                    overlord_spot,
                    is_border,
-                   is_choke }
+                   is_choke,
+                   los_blocker }
     }
 }
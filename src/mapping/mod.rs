@@ -1,8 +1,21 @@
+pub mod attack_positions;
 mod chokes;
 mod climb;
 pub mod connections;
+pub mod decay;
+pub mod distance_fields;
+pub mod drop_spots;
+pub mod formations;
+pub mod hazards;
 pub mod influence;
+pub mod islands;
 pub mod map;
 pub mod map_point;
+pub mod overlord_spots;
+mod shadowcasting;
+pub mod threats;
+pub mod transport;
 pub mod vision;
+pub mod watchtowers;
+pub mod zone_distances;
 pub mod zones;
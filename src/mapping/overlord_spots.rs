@@ -0,0 +1,208 @@
+use pyo3::prelude::*;
+
+use crate::path_find::euclidean_distance;
+
+use super::chokes::Choke;
+use super::map::Map;
+use super::map_point;
+use super::vision::{VisionMap, VisionUnit};
+
+pub const OVERLORD_SIGHT_RANGE: f32 = 11.0;
+pub const ANTI_AIR_RANGE: f32 = 7.0;
+// Spots closer than this to an occupied position are considered taken.
+const OCCUPIED_DISTANCE: f32 = 3.0;
+// How far from the spot we look for ground pathable cells.
+const MAX_GROUND_SEARCH: usize = 30;
+
+#[pyclass]
+#[derive(Clone)]
+pub struct OverlordSpot {
+    pub position: (f32, f32),
+    pub size: usize,
+    pub height: usize,
+    pub ground_distance: Option<f32>,
+    pub sight_range: f32,
+    pub visible_expansions: Vec<usize>,
+    pub visible_chokes: Vec<usize>,
+    pub exposure: f32,
+}
+
+#[pymethods]
+impl OverlordSpot {
+    #[getter(position)]
+    fn get_position(&self) -> (f32, f32) { self.position }
+
+    /// Amount of grid cells in the plateau
+    #[getter(size)]
+    fn get_size(&self) -> usize { self.size }
+
+    #[getter(height)]
+    fn get_height(&self) -> usize { self.height }
+
+    /// Distance to the closest ground pathable cell, None if there is none within the search radius
+    #[getter(ground_distance)]
+    fn get_ground_distance(&self) -> Option<f32> { self.ground_distance }
+
+    /// Indices of the expansions given to `calculate_overlord_spots` that are visible from the spot
+    #[getter(visible_expansions)]
+    fn get_visible_expansions(&self) -> Vec<usize> { self.visible_expansions.clone() }
+
+    /// Indices of the map chokes that are visible from the spot
+    #[getter(visible_chokes)]
+    fn get_visible_chokes(&self) -> Vec<usize> { self.visible_chokes.clone() }
+
+    /// Weighted count of ground cells from which ground anti-air can hit the spot
+    #[getter(exposure)]
+    fn get_exposure(&self) -> f32 { self.exposure }
+}
+
+impl OverlordSpot {
+    pub fn new(position: (f32, f32), size: usize, height: usize) -> Self {
+        OverlordSpot { position,
+                       size,
+                       height,
+                       ground_distance: None,
+                       sight_range: OVERLORD_SIGHT_RANGE,
+                       visible_expansions: Vec::new(),
+                       visible_chokes: Vec::new(),
+                       exposure: 0.0 }
+    }
+
+    /// Lower is better. Spots that see the base are only penalized by their exposure.
+    fn scouting_score(&self, base: (f32, f32)) -> f32 {
+        let d = euclidean_distance(self.position, base);
+        f32::max(0.0, d - self.sight_range) + self.exposure
+    }
+}
+
+#[pymethods]
+impl Map {
+    #[getter(overlord_spot_details)]
+    fn get_overlord_spot_details(&self) -> Vec<OverlordSpot> { self.overlord_spots.clone() }
+
+    /// Recalculates visible expansions, chokes and anti-air exposure for all overlord spots.
+    pub fn calculate_overlord_spots(&mut self,
+                                    expansions: Vec<(f32, f32)>,
+                                    sight_range: Option<f32>,
+                                    anti_air_range: Option<f32>) {
+        score_overlord_spots(&mut self.overlord_spots,
+                             &self.points,
+                             &self.chokes,
+                             &expansions,
+                             sight_range.unwrap_or(OVERLORD_SIGHT_RANGE),
+                             anti_air_range.unwrap_or(ANTI_AIR_RANGE));
+    }
+
+    /// Finds the best unoccupied overlord spots for scouting the base, best first.
+    pub fn best_overlord_spots(&self, base: (f32, f32), occupied: Vec<(f32, f32)>, count: usize) -> Vec<OverlordSpot> {
+        let mut spots: Vec<&OverlordSpot> =
+            self.overlord_spots
+                .iter()
+                .filter(|s| occupied.iter().all(|o| euclidean_distance(s.position, *o) > OCCUPIED_DISTANCE))
+                .collect();
+
+        spots.sort_by(|a, b| a.scouting_score(base).total_cmp(&b.scouting_score(base)));
+        spots.into_iter().take(count).cloned().collect()
+    }
+}
+
+pub fn set_ground_distances(spots: &mut [OverlordSpot], points: &[Vec<map_point::MapPoint>]) {
+    for spot in spots.iter_mut() {
+        spot.ground_distance = nearest_walkable_distance(points, spot.position);
+    }
+}
+
+pub fn score_overlord_spots(spots: &mut [OverlordSpot],
+                            points: &[Vec<map_point::MapPoint>],
+                            chokes: &[Choke],
+                            expansions: &[(f32, f32)],
+                            sight_range: f32,
+                            anti_air_range: f32) {
+    let width = points.len();
+    let height = points[0].len();
+
+    for spot in spots.iter_mut() {
+        let mut vision = VisionMap::new_internal(width, height);
+        vision.add_unit(VisionUnit::new(false, true, spot.position, sight_range, None));
+        vision.calculate_vision_map(points);
+
+        spot.sight_range = sight_range;
+        // Expansions outside of the map are never visible
+        spot.visible_expansions = (0..expansions.len()).filter(|i| {
+                                                           is_inside(expansions[*i], width, height)
+                                                           && vision.vision_status(expansions[*i]) > 0
+                                                       })
+                                                       .collect();
+        spot.visible_chokes =
+            (0..chokes.len()).filter(|i| vision.vision_status(choke_center(&chokes[*i])) > 0).collect();
+        spot.exposure = anti_air_exposure(points, spot.position, anti_air_range);
+    }
+}
+
+#[inline]
+fn choke_center(choke: &Choke) -> (f32, f32) {
+    let ((x0, y0), (x1, y1)) = choke.main_line;
+    ((x0 + x1) * 0.5, (y0 + y1) * 0.5)
+}
+
+#[inline]
+fn is_inside(position: (f32, f32), width: usize, height: usize) -> bool {
+    let x = position.0.round();
+    let y = position.1.round();
+    x >= 0.0 && y >= 0.0 && (x as usize) < width && (y as usize) < height
+}
+
+fn nearest_walkable_distance(points: &[Vec<map_point::MapPoint>], position: (f32, f32)) -> Option<f32> {
+    let width = points.len() as i64;
+    let height = points[0].len() as i64;
+    let cx = position.0.round() as i64;
+    let cy = position.1.round() as i64;
+    let mut best: Option<f32> = None;
+
+    // Search in growing rings, a ring can't contain anything closer than its radius.
+    for r in 0..MAX_GROUND_SEARCH as i64 {
+        if matches!(best, Some(b) if b <= r as f32) {
+            break;
+        }
+
+        for x in (cx - r)..=(cx + r) {
+            for y in (cy - r)..=(cy + r) {
+                if (x - cx).abs() != r && (y - cy).abs() != r {
+                    continue;
+                }
+                if x < 0 || y < 0 || x >= width || y >= height || !points[x as usize][y as usize].walkable {
+                    continue;
+                }
+
+                let d = euclidean_distance(position, (x as f32, y as f32));
+                best = Some(best.map_or(d, |b| f32::min(b, d)));
+            }
+        }
+    }
+
+    best
+}
+
+fn anti_air_exposure(points: &[Vec<map_point::MapPoint>], position: (f32, f32), range: f32) -> f32 {
+    let width = points.len() as i64;
+    let height = points[0].len() as i64;
+    let r = range.ceil() as i64;
+    let cx = position.0.round() as i64;
+    let cy = position.1.round() as i64;
+    let mut exposure = 0.0;
+
+    for x in i64::max(0, cx - r)..i64::min(width, cx + r + 1) {
+        for y in i64::max(0, cy - r)..i64::min(height, cy + r + 1) {
+            if !points[x as usize][y as usize].walkable {
+                continue;
+            }
+
+            let d = euclidean_distance(position, (x as f32, y as f32));
+            if d < range {
+                exposure += 1.0 - d / range;
+            }
+        }
+    }
+
+    exposure
+}
@@ -0,0 +1,127 @@
+// Symmetric shadowcasting, see https://www.albertford.com/shadowcasting/
+// Slopes are kept as exact fractions so that visibility is symmetric between any two floor cells.
+
+#[derive(Copy, Clone)]
+struct Slope {
+    num: i64,
+    den: i64,
+}
+
+impl Slope {
+    fn new(num: i64, den: i64) -> Self {
+        Slope { num,
+                den }
+    }
+
+    /// Slope of the left edge of a tile
+    fn of_tile(depth: i64, col: i64) -> Self { Slope::new(2 * col - 1, 2 * depth) }
+
+    /// floor(depth * slope + 0.5)
+    fn round_ties_up(&self, depth: i64) -> i64 { (2 * depth * self.num + self.den).div_euclid(2 * self.den) }
+
+    /// ceil(depth * slope - 0.5)
+    fn round_ties_down(&self, depth: i64) -> i64 { -(self.den - 2 * depth * self.num).div_euclid(2 * self.den) }
+
+    /// col >= depth * slope
+    fn is_below(&self, depth: i64, col: i64) -> bool { col * self.den >= depth * self.num }
+
+    /// col <= depth * slope
+    fn is_above(&self, depth: i64, col: i64) -> bool { col * self.den <= depth * self.num }
+}
+
+struct Row {
+    depth: i64,
+    start_slope: Slope,
+    end_slope: Slope,
+}
+
+impl Row {
+    fn next(&self) -> Row {
+        Row { depth: self.depth + 1,
+              start_slope: self.start_slope,
+              end_slope: self.end_slope }
+    }
+
+    fn is_symmetric(&self, col: i64) -> bool {
+        self.start_slope.is_below(self.depth, col) && self.end_slope.is_above(self.depth, col)
+    }
+}
+
+struct Quadrant {
+    cardinal: u8,
+    origin: (i64, i64),
+}
+
+impl Quadrant {
+    fn transform(&self, depth: i64, col: i64) -> (i64, i64) {
+        let (x, y) = self.origin;
+        match self.cardinal {
+            0 => (x + col, y - depth),
+            1 => (x + depth, y + col),
+            2 => (x + col, y + depth),
+            _ => (x - depth, y + col),
+        }
+    }
+}
+
+/// Calls `reveal` for every cell visible from the origin within the range.
+/// Cells outside of the grid are treated as blocking and blocking cells themselves are never revealed.
+pub fn compute_fov<B, R>(origin: (usize, usize), range: f32, width: usize, height: usize, is_blocking: B, mut reveal: R)
+    where B: Fn(usize, usize) -> bool,
+          R: FnMut(usize, usize)
+{
+    let range2 = range * range;
+    let max_depth = range.ceil() as i64;
+    let origin_i = (origin.0 as i64, origin.1 as i64);
+
+    let in_grid = |(x, y): (i64, i64)| x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height;
+    let blocking = |pos: (i64, i64)| !in_grid(pos) || is_blocking(pos.0 as usize, pos.1 as usize);
+    let in_range = |(x, y): (i64, i64)| {
+        let dx = (x - origin_i.0) as f32;
+        let dy = (y - origin_i.1) as f32;
+        dx * dx + dy * dy <= range2
+    };
+
+    reveal(origin.0, origin.1);
+
+    for cardinal in 0..4 {
+        let quadrant = Quadrant { cardinal,
+                                  origin: origin_i };
+        let mut rows = vec![Row { depth: 1,
+                                  start_slope: Slope::new(-1, 1),
+                                  end_slope: Slope::new(1, 1) }];
+
+        while let Some(mut row) = rows.pop() {
+            if row.depth > max_depth {
+                continue;
+            }
+
+            let min_col = row.start_slope.round_ties_up(row.depth);
+            let max_col = row.end_slope.round_ties_down(row.depth);
+            let mut prev_blocking: Option<bool> = None;
+
+            for col in min_col..=max_col {
+                let pos = quadrant.transform(row.depth, col);
+                let wall = blocking(pos);
+
+                if !wall && row.is_symmetric(col) && in_range(pos) {
+                    reveal(pos.0 as usize, pos.1 as usize);
+                }
+                if prev_blocking == Some(true) && !wall {
+                    row.start_slope = Slope::of_tile(row.depth, col);
+                }
+                if prev_blocking == Some(false) && wall {
+                    let mut next_row = row.next();
+                    next_row.end_slope = Slope::of_tile(row.depth, col);
+                    rows.push(next_row);
+                }
+
+                prev_blocking = Some(wall);
+            }
+
+            if prev_blocking == Some(false) {
+                rows.push(row.next());
+            }
+        }
+    }
+}
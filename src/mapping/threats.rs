@@ -0,0 +1,95 @@
+use pyo3::prelude::*;
+
+use crate::helpers::round_point2;
+
+use super::influence::{add_fading_influence, MAPS_AIR, MAPS_BOTH, MAPS_GROUND};
+use super::map::Map;
+
+const DEFAULT_FADE_MARGIN: f32 = 2.0;
+
+/// Enemy unit weapon described for influence calculations.
+/// Ranges are measured from the edge of the unit like in the game.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct ThreatUnit {
+    pub position: (f32, f32),
+    pub weapon_range: f32,
+    pub min_range: f32,
+    pub dps: f32,
+    pub hits_ground: bool,
+    pub hits_air: bool,
+    pub radius: f32,
+}
+
+#[pymethods]
+impl ThreatUnit {
+    #[new]
+    pub fn new(position: (f32, f32),
+               weapon_range: f32,
+               min_range: f32,
+               dps: f32,
+               hits_ground: bool,
+               hits_air: bool,
+               radius: f32)
+               -> Self {
+        ThreatUnit { position,
+                     weapon_range,
+                     min_range,
+                     dps,
+                     hits_ground,
+                     hits_air,
+                     radius }
+    }
+
+    #[getter(position)]
+    fn get_position(&self) -> (f32, f32) { self.position }
+
+    #[getter(weapon_range)]
+    fn get_weapon_range(&self) -> f32 { self.weapon_range }
+
+    /// Targets closer than this can't be hit, 0 for most units
+    #[getter(min_range)]
+    fn get_min_range(&self) -> f32 { self.min_range }
+
+    #[getter(dps)]
+    fn get_dps(&self) -> f32 { self.dps }
+
+    #[getter(hits_ground)]
+    fn get_hits_ground(&self) -> bool { self.hits_ground }
+
+    #[getter(hits_air)]
+    fn get_hits_air(&self) -> bool { self.hits_air }
+
+    #[getter(radius)]
+    fn get_radius(&self) -> f32 { self.radius }
+}
+
+#[pymethods]
+impl Map {
+    /// Adds dps as influence to the maps each unit can hit.
+    /// Influence is flat up to weapon range plus unit radius and fades out over the fade margin.
+    /// Area inside minimum range is left untouched.
+    pub fn add_threats(&mut self, units: Vec<ThreatUnit>, fade_margin: Option<f32>) {
+        let fade_margin = fade_margin.unwrap_or(DEFAULT_FADE_MARGIN);
+
+        for unit in &units {
+            let map_type = match (unit.hits_ground, unit.hits_air) {
+                (true, true) => MAPS_BOTH,
+                (true, false) => MAPS_GROUND,
+                (false, true) => MAPS_AIR,
+                (false, false) => continue,
+            };
+
+            let hollow = if unit.min_range > 0.0 {
+                unit.min_range + unit.radius
+            } else {
+                0.0
+            };
+            let full = unit.weapon_range + unit.radius;
+            let position = round_point2(unit.position);
+            let mut maps = self.get_influence_maps(map_type);
+
+            add_fading_influence(&mut maps, position, unit.dps, hollow, full, full + fade_margin);
+        }
+    }
+}
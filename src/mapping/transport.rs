@@ -0,0 +1,168 @@
+use pathfinding::prelude::astar;
+use pyo3::prelude::*;
+
+use crate::helpers::round_point2;
+use crate::path_find::pos::{self, NormalPosAPI, Pos, PositionAPI};
+use crate::path_find::PathFind;
+
+use super::map::Map;
+
+pub const TRAVEL_WALK: u8 = 0;
+pub const TRAVEL_FLY: u8 = 1;
+
+const DEFAULT_PICKUP_COST: f32 = 2.0;
+const DEFAULT_DROP_COST: f32 = 2.0;
+
+/// Part of a transport plan that is either walked or flown
+#[pyclass]
+#[derive(Clone)]
+pub struct TransportLeg {
+    pub mode: u8,
+    pub path: Vec<(usize, usize)>,
+    pub cost: f32,
+}
+
+#[pymethods]
+impl TransportLeg {
+    /// 0 for walking, 1 for flying in a transport
+    #[getter(mode)]
+    fn get_mode(&self) -> u8 { self.mode }
+
+    #[getter(path)]
+    fn get_path(&self) -> Vec<(usize, usize)> { self.path.clone() }
+
+    /// Cost of the leg in walking distance, flying legs include pickup and drop costs
+    #[getter(cost)]
+    fn get_cost(&self) -> f32 { self.cost }
+}
+
+/// Search state, position and whether the unit is in the transport
+type State = (Pos, bool);
+
+struct TransportCosts<'a> {
+    ground: &'a PathFind,
+    air: &'a PathFind,
+    air_speed: f32,
+    pickup: usize,
+    drop: usize,
+}
+
+impl TransportCosts<'_> {
+    /// Influence weighted cost of moving from the cell, in walking distance multiplied by pos::MULT
+    fn successors(&self, state: &State) -> Vec<(State, usize)> {
+        let (position, flying) = state;
+        let api = NormalPosAPI();
+        let mut result: Vec<(State, usize)> = Vec::with_capacity(9);
+
+        if *flying {
+            for (next, cost) in api.successors(position, &self.air.map) {
+                let value = self.air.map[next.0][next.1] as f32 / self.air.normal_influence as f32;
+                result.push(((next, true), (cost as f32 * value / self.air_speed) as usize));
+            }
+        } else {
+            for (next, cost) in api.successors(position, &self.ground.map) {
+                let value = self.ground.map[next.0][next.1] as f32 / self.ground.normal_influence as f32;
+                result.push(((next, false), (cost as f32 * value) as usize));
+            }
+        }
+
+        // The transport can pick up and drop where both ground and air are pathable
+        if self.ground.map[position.0][position.1] > 0 && self.air.map[position.0][position.1] > 0 {
+            let transfer = if *flying {
+                self.drop
+            } else {
+                self.pickup
+            };
+            result.push(((position.clone(), !flying), transfer));
+        }
+
+        result
+    }
+
+    fn step_cost(&self, from: &State, to: &State) -> usize {
+        self.successors(from).into_iter().find(|(s, _)| s == to).map(|(_, c)| c).unwrap_or(0)
+    }
+}
+
+#[pymethods]
+impl Map {
+    /// Finds the cheapest way from start to goal combining walking on ground pathing
+    /// and flying in a transport on air pathing, both take influence into account.
+    /// Returns the legs of the plan in order and the total cost in walking distance, None if the goal can't be reached.
+    /// Start at the goal gives a single walking leg without movement.
+    ///     air_speed:
+    ///         speed of the transport relative to the walking speed of the unit, 1 by default, None if not positive
+    ///     pickup_cost, drop_cost:
+    ///         overhead of getting in and out of the transport in walking distance, 2 by default
+    pub fn find_transport_path(&self,
+                               start: (f32, f32),
+                               goal: (f32, f32),
+                               air_speed: Option<f32>,
+                               pickup_cost: Option<f32>,
+                               drop_cost: Option<f32>)
+                               -> Option<(Vec<TransportLeg>, f32)> {
+        let air_speed = air_speed.unwrap_or(1.0);
+        if air_speed.is_nan() || air_speed <= 0.0 {
+            return None;
+        }
+
+        let costs = TransportCosts { ground: &self.ground_pathing,
+                                     air: &self.air_pathing,
+                                     air_speed,
+                                     pickup: (pickup_cost.unwrap_or(DEFAULT_PICKUP_COST) * pos::MULTF32) as usize,
+                                     drop: (drop_cost.unwrap_or(DEFAULT_DROP_COST) * pos::MULTF32) as usize };
+
+        let start = self.ground_pathing.get_closest_pathable(round_point2(start));
+        let goal = self.ground_pathing.get_closest_pathable(round_point2(goal));
+        let goal_pos = Pos(goal.0, goal.1);
+        let api = NormalPosAPI();
+        // Fastest possible travel for an admissible heuristic
+        let heuristic_mult = f32::min(1.0, 1.0 / air_speed);
+
+        let (states, total) = astar(&(Pos(start.0, start.1), false),
+                                    |s| costs.successors(s),
+                                    |s| (api.octile_distance(&s.0, &goal_pos) as f32 * heuristic_mult) as usize,
+                                    |s| s.0 == goal_pos && !s.1)?;
+
+        Some((legs(&states, &costs), total as f32 / pos::MULTF32))
+    }
+}
+
+/// Splits the states into walking and flying legs, transfers belong to the flying leg
+fn legs(states: &[State], costs: &TransportCosts) -> Vec<TransportLeg> {
+    let mut legs: Vec<TransportLeg> = Vec::new();
+    let mut current = TransportLeg { mode: TRAVEL_WALK,
+                                     path: vec![(states[0].0 .0, states[0].0 .1)],
+                                     cost: 0.0 };
+
+    for step in states.windows(2) {
+        let (from, to) = (&step[0], &step[1]);
+        let cost = costs.step_cost(from, to) as f32 / pos::MULTF32;
+        let cell = (to.0 .0, to.0 .1);
+
+        if from.1 == to.1 {
+            current.path.push(cell);
+            current.cost += cost;
+        } else if to.1 {
+            // Picked up
+            legs.push(current);
+            current = TransportLeg { mode: TRAVEL_FLY,
+                                     path: vec![cell],
+                                     cost };
+        } else {
+            // Dropped
+            current.cost += cost;
+            legs.push(current);
+            current = TransportLeg { mode: TRAVEL_WALK,
+                                     path: vec![cell],
+                                     cost: 0.0 };
+        }
+    }
+
+    legs.push(current);
+    // Walking legs without any movement come from being picked up at the start or dropped at the goal
+    if legs.len() > 1 {
+        legs.retain(|leg| leg.mode == TRAVEL_FLY || leg.path.len() > 1);
+    }
+    legs
+}
@@ -1,4 +1,5 @@
 use pyo3::prelude::*;
+use std::collections::HashMap;
 
 use crate::{
     helpers::round_point2,
@@ -6,6 +7,7 @@ use crate::{
 };
 
 use super::map_point;
+use super::shadowcasting::compute_fov;
 #[derive(Copy, Clone)]
 pub enum VisionStatus {
     NotSeen,
@@ -14,6 +16,18 @@ pub enum VisionStatus {
     Detected,
 }
 
+impl VisionStatus {
+    #[inline]
+    fn from_counts(seen: u16, detected: u16) -> Self {
+        match (seen > 0, detected > 0) {
+            (false, false) => VisionStatus::NotSeen,
+            (true, false) => VisionStatus::Seen,
+            (true, true) => VisionStatus::Detected,
+            (false, true) => VisionStatus::NotSeenButDetected,
+        }
+    }
+}
+
 #[pyclass]
 #[derive(Clone, Debug)]
 pub struct VisionUnit {
@@ -21,23 +35,59 @@ pub struct VisionUnit {
     flying: bool,
     position: (f32, f32),
     sight_range: f32,
+    detection_range: Option<f32>,
 }
 
 #[pymethods]
 impl VisionUnit {
+    /// Detectors without a detection range detect their whole sight range.
     #[new]
-    pub fn new(detector: bool, flying: bool, position: (f32, f32), sight_range: f32) -> Self {
+    pub fn new(detector: bool,
+               flying: bool,
+               position: (f32, f32),
+               sight_range: f32,
+               detection_range: Option<f32>)
+               -> Self {
         VisionUnit { detector,
                      flying,
                      position,
-                     sight_range }
+                     sight_range,
+                     detection_range }
+    }
+}
+
+impl VisionUnit {
+    #[inline]
+    pub fn detection_range(&self) -> Option<f32> {
+        match (self.detector, self.detection_range) {
+            (_, Some(range)) if range > 0.0 => Some(range),
+            (true, _) => Some(self.sight_range),
+            _ => None,
+        }
     }
 }
 
+/// Cells a single unit sees and detects, kept so that the unit can be removed later on.
+#[derive(Clone, Debug, Default)]
+pub struct VisionContribution {
+    seen: Vec<(usize, usize)>,
+    detected: Vec<(usize, usize)>,
+}
+
+struct TrackedUnit {
+    unit: VisionUnit,
+    contribution: VisionContribution,
+}
+
 #[pyclass]
 pub struct VisionMap {
     units: Vec<VisionUnit>,
+    tracked_units: HashMap<u64, TrackedUnit>,
+    seen_counts: Vec<Vec<u16>>,
+    detected_counts: Vec<Vec<u16>>,
     pub points: Vec<Vec<VisionStatus>>,
+    /// Game loop when the cell was last seen or detected, 0 if never.
+    pub last_seen: Vec<Vec<u32>>,
     width: usize,
     height: usize,
 }
@@ -46,23 +96,63 @@ pub struct VisionMap {
 impl VisionMap {
     pub fn clear(&mut self) {
         self.units.clear();
+        self.tracked_units.clear();
 
         for y in &mut self.points {
             for x in y {
                 *x = VisionStatus::NotSeen;
             }
         }
+        for counts in [&mut self.seen_counts, &mut self.detected_counts] {
+            for y in counts.iter_mut() {
+                for x in y {
+                    *x = 0;
+                }
+            }
+        }
     }
 
+    /// Adds a unit that is applied on the next `calculate_vision_map` call.
     pub fn add_unit(&mut self, unit: VisionUnit) { self.units.push(unit); }
 
     pub fn vision_status(&self, position: (f32, f32)) -> usize {
         let int_point = round_point2(position);
         match self.points[int_point.0][int_point.1] {
             VisionStatus::NotSeen => 0,
-            VisionStatus::NotSeenButDetected => 0,
             VisionStatus::Seen => 1,
             VisionStatus::Detected => 2,
+            VisionStatus::NotSeenButDetected => 3,
+        }
+    }
+
+    /// Marks all currently seen or detected cells as seen on this game loop.
+    /// Memory is not affected by `clear`.
+    pub fn update_memory(&mut self, game_loop: u32) {
+        for x in 0..self.width {
+            for y in 0..self.height {
+                if !matches!(self.points[x][y], VisionStatus::NotSeen) {
+                    self.last_seen[x][y] = game_loop;
+                }
+            }
+        }
+    }
+
+    /// Game loops since the position was last seen or detected.
+    pub fn staleness(&self, position: (f32, f32), game_loop: u32) -> u32 {
+        let int_point = round_point2(position);
+        game_loop.saturating_sub(self.last_seen[int_point.0][int_point.1])
+    }
+
+    pub fn has_unit(&self, unit_id: u64) -> bool { self.tracked_units.contains_key(&unit_id) }
+
+    /// Removes a unit added with `set_unit`. Returns false if the unit wasn't found.
+    pub fn remove_unit(&mut self, unit_id: u64) -> bool {
+        match self.tracked_units.remove(&unit_id) {
+            Some(tracked) => {
+                self.remove_contribution(&tracked.contribution);
+                true
+            }
+            None => false,
         }
     }
 }
@@ -70,34 +160,87 @@ impl VisionMap {
 impl VisionMap {
     pub fn new_internal(width: usize, height: usize) -> Self {
         let units = vec![];
+        let tracked_units = HashMap::new();
+        let seen_counts = vec![vec![0; height]; width];
+        let detected_counts = vec![vec![0; height]; width];
         let points = vec![vec![VisionStatus::NotSeen; height]; width];
+        let last_seen = vec![vec![0; height]; width];
         let width = points.len();
         let height = points[0].len();
 
         VisionMap { units,
+                    tracked_units,
+                    seen_counts,
+                    detected_counts,
                     points,
+                    last_seen,
                     width,
                     height }
     }
 
-    pub fn calculate_vision_map(&mut self, map_points: &Vec<Vec<map_point::MapPoint>>) {
-        for unit in self.units.iter() {
-            if unit.flying {
-                if unit.detector {
-                    set_detection(&mut self.points, &unit.position, unit.sight_range);
-                } else {
-                    set_vision(&mut self.points, &unit.position, unit.sight_range);
-                }
-            } else {
-                if unit.detector {
-                    calc_ground_detection(&mut self.points, map_points, &unit.position, unit.sight_range);
-                } else {
-                    calc_ground_vision(&mut self.points, map_points, &unit.position, unit.sight_range);
-                }
-            }
+    /// Applies vision of all units added with `add_unit` since the last call.
+    pub fn calculate_vision_map(&mut self, map_points: &[Vec<map_point::MapPoint>]) {
+        let units = std::mem::take(&mut self.units);
+
+        for unit in units.iter() {
+            let contribution = calc_contribution(unit, map_points);
+            self.add_contribution(&contribution);
+        }
+    }
+
+    /// Adds or replaces a unit with the id, only cells that the unit sees or saw are updated.
+    pub fn set_unit(&mut self, unit_id: u64, unit: VisionUnit, map_points: &[Vec<map_point::MapPoint>]) {
+        let contribution = calc_contribution(&unit, map_points);
+        self.add_contribution(&contribution);
+
+        if let Some(old) = self.tracked_units.insert(unit_id,
+                                                     TrackedUnit { unit,
+                                                                   contribution })
+        {
+            self.remove_contribution(&old.contribution);
+        }
+    }
+
+    /// Moves a unit added with `set_unit`. Returns false if the unit wasn't found.
+    pub fn move_unit(&mut self, unit_id: u64, position: (f32, f32), map_points: &[Vec<map_point::MapPoint>]) -> bool {
+        let unit = match self.tracked_units.get(&unit_id) {
+            Some(tracked) if round_point2(tracked.unit.position) == round_point2(position) => return true,
+            Some(tracked) => VisionUnit { position,
+                                          ..tracked.unit.clone() },
+            None => return false,
+        };
+
+        self.set_unit(unit_id, unit, map_points);
+        true
+    }
+
+    fn add_contribution(&mut self, contribution: &VisionContribution) {
+        for &(x, y) in &contribution.seen {
+            self.seen_counts[x][y] += 1;
+            self.update_status(x, y);
+        }
+        for &(x, y) in &contribution.detected {
+            self.detected_counts[x][y] += 1;
+            self.update_status(x, y);
+        }
+    }
+
+    fn remove_contribution(&mut self, contribution: &VisionContribution) {
+        for &(x, y) in &contribution.seen {
+            self.seen_counts[x][y] -= 1;
+            self.update_status(x, y);
+        }
+        for &(x, y) in &contribution.detected {
+            self.detected_counts[x][y] -= 1;
+            self.update_status(x, y);
         }
     }
 
+    #[inline]
+    fn update_status(&mut self, x: usize, y: usize) {
+        self.points[x][y] = VisionStatus::from_counts(self.seen_counts[x][y], self.detected_counts[x][y]);
+    }
+
     pub fn draw_vision(&self) -> Vec<Vec<usize>> {
         let mut vision_map = vec![vec![0; self.height]; self.width];
 
@@ -105,9 +248,9 @@ impl VisionMap {
             for y in 0..self.height {
                 match self.points[x][y] {
                     VisionStatus::NotSeen => vision_map[x][y] = 0,
-                    VisionStatus::NotSeenButDetected => vision_map[x][y] = 0,
                     VisionStatus::Seen => vision_map[x][y] = 1,
                     VisionStatus::Detected => vision_map[x][y] = 2,
+                    VisionStatus::NotSeenButDetected => vision_map[x][y] = 3,
                 }
             }
         }
@@ -116,56 +259,33 @@ impl VisionMap {
     }
 }
 
-fn set_detection(points: &mut Vec<Vec<VisionStatus>>, position: &(f32, f32), sight_range: f32) {
-    let u_position = round_point2(*position);
-    let size = ((sight_range * 2f32) as usize, (sight_range * 2f32) as usize);
-    let width = points.len();
-    let height = points[0].len();
-
-    let rect = rectangle::Rectangle::init_from_center2(u_position, size, width, height);
-
-    for x in rect.x..rect.x_end {
-        for y in rect.y..rect.y_end {
-            let d = octile_distance_f32(u_position, (x, y));
-
-            if d <= sight_range {
-                points[x][y] = VisionStatus::Detected;
-            }
-        }
+fn calc_contribution(unit: &VisionUnit, map_points: &[Vec<map_point::MapPoint>]) -> VisionContribution {
+    let width = map_points.len();
+    let height = map_points[0].len();
+    let mut contribution = VisionContribution::default();
+
+    // Seen and detected areas are independent, detection isn't blocked by terrain.
+    if unit.flying {
+        circle_cells(&unit.position, unit.sight_range, width, height, |x, y| contribution.seen.push((x, y)));
+    } else {
+        calc_ground_vision(map_points, &unit.position, unit.sight_range, |x, y| contribution.seen.push((x, y)));
+        // Shadowcasting quadrants overlap on the diagonals
+        contribution.seen.sort_unstable();
+        contribution.seen.dedup();
     }
-}
-
-fn set_vision(points: &mut Vec<Vec<VisionStatus>>, position: &(f32, f32), sight_range: f32) {
-    let u_position = round_point2(*position);
-    let size = ((sight_range * 2f32) as usize, (sight_range * 2f32) as usize);
-    let width = points.len();
-    let height = points[0].len();
-
-    let rect = rectangle::Rectangle::init_from_center2(u_position, size, width, height);
-
-    for x in rect.x..rect.x_end {
-        for y in rect.y..rect.y_end {
-            let d = octile_distance_f32(u_position, (x, y));
 
-            if d <= sight_range {
-                if matches!(points[x][y], VisionStatus::NotSeen) {
-                    points[x][y] = VisionStatus::Seen;
-                } else if matches!(points[x][y], VisionStatus::NotSeenButDetected) {
-                    points[x][y] = VisionStatus::Detected;
-                }
-            }
-        }
+    if let Some(detection_range) = unit.detection_range() {
+        circle_cells(&unit.position, detection_range, width, height, |x, y| contribution.detected.push((x, y)));
     }
+
+    contribution
 }
 
-fn calc_ground_detection(points: &mut Vec<Vec<VisionStatus>>,
-                         map_points: &Vec<Vec<map_point::MapPoint>>,
-                         position: &(f32, f32),
-                         sight_range: f32) {
+fn circle_cells<F>(position: &(f32, f32), range: f32, width: usize, height: usize, mut mark: F)
+    where F: FnMut(usize, usize)
+{
     let u_position = round_point2(*position);
-    let size = ((sight_range * 2f32) as usize, (sight_range * 2f32) as usize);
-    let width = points.len();
-    let height = points[0].len();
+    let size = ((range * 2f32) as usize, (range * 2f32) as usize);
 
     let rect = rectangle::Rectangle::init_from_center2(u_position, size, width, height);
 
@@ -173,103 +293,47 @@ fn calc_ground_detection(points: &mut Vec<Vec<VisionStatus>>,
         for y in rect.y..rect.y_end {
             let d = octile_distance_f32(u_position, (x, y));
 
-            if d <= sight_range {
-                if matches!(points[x][y], VisionStatus::NotSeen)
-                   || matches!(points[x][y], VisionStatus::NotSeenButDetected)
-                {
-                    points[x][y] = VisionStatus::NotSeenButDetected;
-                } else {
-                    points[x][y] = VisionStatus::Detected;
-                }
+            if d <= range {
+                mark(x, y);
             }
         }
     }
+}
 
-    let circumference = 2f32 * sight_range * std::f32::consts::PI;
-    let rays = circumference as usize;
-    let step_mult = 1.3f32;
-    let steps = (sight_range * step_mult) as usize;
-
+pub(crate) fn calc_ground_vision<F>(map_points: &[Vec<map_point::MapPoint>],
+                                    position: &(f32, f32),
+                                    sight_range: f32,
+                                    mark: F)
+    where F: FnMut(usize, usize)
+{
     let u_position = round_point2(*position);
-    let current_height = map_points[u_position.0][u_position.1].height;
-    let mut max_height_seen = current_height / 8 * 8 + 7;
-
-    if current_height % 8 >= 4 {
-        max_height_seen += 8;
-    }
-
-    for index in 0..rays {
-        let angle = index as f32 / rays as f32 * 2f32 * std::f32::consts::PI;
-        let v_x = angle.sin();
-        let v_y = angle.cos();
-
-        for step in 0..steps {
-            // Rays are only drawn until non-walkable is found and thus the cannot go out of bounds
-            let step_f32 = step as f32 / step_mult;
-            let new_pos =
-                ((position.0 as f32 + v_x * step_f32) as usize, (position.1 as f32 + v_y * step_f32) as usize);
-
-            // TODO: Same for height difference
-            if map_points[new_pos.0][new_pos.1].height > max_height_seen {
-                // Ray can't reach further
-                break;
-            }
-
-            points[new_pos.0][new_pos.1] = VisionStatus::Detected;
-        }
-    }
+    let width = map_points.len();
+    let height = map_points[0].len();
+    let max_height_seen = max_height_seen(map_points, u_position);
+    // Units inside line of sight blockers can see out of them
+    let see_through_blockers = map_points[u_position.0][u_position.1].los_blocker;
+
+    compute_fov(u_position,
+                sight_range,
+                width,
+                height,
+                |x, y| {
+                    let point = &map_points[x][y];
+                    point.height > max_height_seen || (point.los_blocker && !see_through_blockers)
+                },
+                mark);
 }
 
-fn calc_ground_vision(points: &mut Vec<Vec<VisionStatus>>,
-                      map_points: &Vec<Vec<map_point::MapPoint>>,
-                      position: &(f32, f32),
-                      sight_range: f32) {
-    let circumference = 2f32 * sight_range * std::f32::consts::PI;
-    let rays = circumference as usize;
-    let step_mult = 1.3f32;
-    let steps = (sight_range * step_mult) as usize;
-
-    // println!("Rays {} and steps {}", rays, steps);
-
-    let u_position = round_point2(*position);
-    let current_height = map_points[u_position.0][u_position.1].height;
+/// Ground units see their own height level and everything below it.
+/// Cells are rounded to the closest level as sc2 height levels aren't exact in the height map.
+#[inline]
+pub(crate) fn max_height_seen(map_points: &[Vec<map_point::MapPoint>], position: (usize, usize)) -> usize {
+    let current_height = map_points[position.0][position.1].height;
     let mut max_height_seen = current_height / 8 * 8 + 7;
 
     if current_height % 8 >= 4 {
         max_height_seen += 8;
     }
 
-    for index in 0..rays {
-        let angle = index as f32 / rays as f32 * 2f32 * std::f32::consts::PI;
-        let v_x = angle.sin();
-        let v_y = angle.cos();
-
-        for step in 0..steps {
-            // Rays are only drawn until non-walkable is found and thus the cannot go out of bounds
-            let step_f32 = step as f32 / step_mult;
-            let new_pos = ((position.0 + v_x * step_f32) as usize, (position.1 + v_y * step_f32) as usize);
-
-            if map_points[new_pos.0][new_pos.1].height > max_height_seen {
-                // Ray can't reach further
-                // println!("Ray {} stopped at ({}, {}), angle was {} and vector was ({}, {}) with step {}",
-                //          index, new_pos.0, new_pos.1, angle, v_x, v_y, step_f32);
-                break;
-            }
-            let status = points[new_pos.0][new_pos.1];
-
-            if matches!(status, VisionStatus::NotSeen) {
-                // if new_pos.0 == 25 && new_pos.1 == 8 {
-                // println!("Ray {} set vision to ({}, {}), angle was {} and vector was ({}, {}) with step {}",
-                //          index, new_pos.0, new_pos.1, angle, v_x, v_y, step_f32);
-                // }
-                points[new_pos.0][new_pos.1] = VisionStatus::Seen;
-            } else if matches!(status, VisionStatus::NotSeenButDetected) {
-                // if new_pos.0 == 25 && new_pos.1 == 8 {
-                // println!("Ray {} set vision to ({}, {}), angle was {} and vector was ({}, {}) with step {}",
-                //          index, new_pos.0, new_pos.1, angle, v_x, v_y, step_f32);
-                // }
-                points[new_pos.0][new_pos.1] = VisionStatus::Detected;
-            }
-        }
-    }
+    max_height_seen
 }
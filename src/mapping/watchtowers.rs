@@ -0,0 +1,96 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::path_find::euclidean_distance;
+
+use super::map::Map;
+use super::vision::VisionUnit;
+
+// Watchtower vision is tracked in the vision map with ids counting down from the maximum
+// so that they don't collide with unit tags.
+const WATCHTOWER_ID_BASE: u64 = u64::MAX;
+// How far from the tower center an occupying unit can be.
+const WATCHTOWER_MATCH_DISTANCE: f32 = 3.0;
+
+#[derive(Clone, Debug)]
+pub struct Watchtower {
+    pub position: (f32, f32),
+    pub radius: f32,
+    pub occupied: bool,
+}
+
+#[pymethods]
+impl Map {
+    /// Sets line of sight blockers, any value above 0 blocks ground vision.
+    /// Units inside blockers can see out of them. The grid must have the size of the map.
+    pub fn set_los_blockers(&mut self, blockers: Vec<Vec<usize>>) -> PyResult<()> {
+        let width = self.points.len();
+        let height = self.points[0].len();
+        if blockers.len() != width || blockers.iter().any(|column| column.len() != height) {
+            return Err(PyValueError::new_err(format!("Line of sight blockers must be a {}x{} grid", width, height)));
+        }
+
+        for (x, column) in blockers.iter().enumerate() {
+            for (y, value) in column.iter().enumerate() {
+                self.points[x][y].los_blocker = *value > 0;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets Xel'Naga watchtowers as a list of positions and vision radii. All towers start unoccupied.
+    pub fn set_watchtowers(&mut self, towers: Vec<((f32, f32), f32)>) {
+        for index in 0..self.watchtowers.len() {
+            self.vision_map.remove_unit(WATCHTOWER_ID_BASE - index as u64);
+        }
+
+        self.watchtowers = towers.into_iter()
+                                 .map(|(position, radius)| Watchtower { position,
+                                                                        radius,
+                                                                        occupied: false })
+                                 .collect();
+    }
+
+    #[getter(watchtowers)]
+    fn get_watchtowers(&self) -> Vec<((f32, f32), f32, bool)> {
+        self.watchtowers.iter().map(|t| (t.position, t.radius, t.occupied)).collect()
+    }
+
+    /// Sets whether the watchtower closest to the position is occupied and grants vision.
+    /// Returns false if there is no watchtower near the position.
+    pub fn set_watchtower_occupied(&mut self, position: (f32, f32), occupied: bool) -> bool {
+        let found =
+            self.watchtowers.iter().position(|t| euclidean_distance(t.position, position) <= WATCHTOWER_MATCH_DISTANCE);
+
+        match found {
+            Some(index) => {
+                self.watchtowers[index].occupied = occupied;
+                self.apply_watchtower(index);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Map {
+    /// Adds vision of all occupied watchtowers to the vision map.
+    pub fn apply_watchtowers(&mut self) {
+        for index in 0..self.watchtowers.len() {
+            self.apply_watchtower(index);
+        }
+    }
+
+    fn apply_watchtower(&mut self, index: usize) {
+        let tower = &self.watchtowers[index];
+        let unit_id = WATCHTOWER_ID_BASE - index as u64;
+
+        if tower.occupied {
+            // Towers see over cliffs like air units do
+            let unit = VisionUnit::new(false, true, tower.position, tower.radius, None);
+            self.vision_map.set_unit(unit_id, unit, &self.points);
+        } else {
+            self.vision_map.remove_unit(unit_id);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+
+use pyo3::prelude::*;
+
+use crate::helpers::point2_f32;
+use crate::path_find::rectangle::Rectangle;
+use crate::path_find::{octile_distance_f32, PathFind};
+
+use super::distance_fields::DistanceField;
+use super::map::Map;
+
+const MAP_TYPES: [u8; 4] = [0, 1, 2, 3];
+/// Map types that blocks change, air isn't blocked
+const BLOCKED_MAP_TYPES: [u8; 3] = [0, 1, 2];
+/// Above this many changed rectangles all distances are calculated again
+const MAX_CHANGED_RECTANGLES: usize = 256;
+
+/// Walking distances between zone centers for one map type
+pub struct ZoneDistances {
+    /// Distances between zones with zone index - 1 as index, None when not connected
+    pub distances: Vec<Vec<Option<f32>>>,
+    /// Shortest path between zones i < j at paths[i][j], empty when not connected
+    paths: Vec<Vec<Vec<(usize, usize)>>>,
+    /// Closest pathable cells to the zone centers
+    centers: Vec<(usize, usize)>,
+    /// Rectangles where blocks may have changed the pathing since the distances were calculated,
+    /// with whether their cells were pathable before the change
+    changed: HashMap<Rectangle, Vec<bool>>,
+    /// Set when too many rectangles have changed to check them one by one
+    all_changed: bool,
+}
+
+impl ZoneDistances {
+    pub fn new(map: &PathFind, zone_centers: &[(usize, usize)], map_type: u8) -> Self {
+        let centers: Vec<(usize, usize)> = zone_centers.iter().map(|c| map.get_closest_pathable(*c)).collect();
+        let count = centers.len();
+        let mut zone_distances = ZoneDistances { distances: vec![vec![None; count]; count],
+                                                 paths: vec![vec![Vec::new(); count]; count],
+                                                 centers,
+                                                 changed: HashMap::new(),
+                                                 all_changed: false };
+
+        for i in 0..count {
+            zone_distances.distances[i][i] = Some(0.0);
+            let others: Vec<usize> = (i + 1..count).collect();
+            zone_distances.calculate_from(&map.map, i, &others, map_type);
+        }
+
+        zone_distances
+    }
+
+    /// Remembers the rectangle and its cells before they change to check the distances against on the next lookup
+    pub fn mark_changed(&mut self, grid: &[Vec<usize>], rect: Rectangle) {
+        if self.all_changed || self.changed.contains_key(&rect) {
+            return;
+        }
+
+        let pathable = pathable_cells(grid, &rect);
+        self.changed.insert(rect, pathable);
+        if self.changed.len() > MAX_CHANGED_RECTANGLES {
+            self.all_changed = true;
+            self.changed.clear();
+        }
+    }
+
+    /// Recalculates the distances between the zones that the changed rectangles may have affected
+    fn update(&mut self, map: &PathFind, zone_centers: &[(usize, usize)], map_type: u8) {
+        if self.all_changed {
+            *self = ZoneDistances::new(map, zone_centers, map_type);
+            return;
+        }
+
+        // Blocks that are created again the same way after a reset don't change anything
+        let rects: Vec<Rectangle> = self.changed
+                                        .drain()
+                                        .filter(|(rect, pathable)| pathable_cells(&map.map, rect) != *pathable)
+                                        .map(|(rect, _)| rect)
+                                        .collect();
+        if rects.is_empty() {
+            return;
+        }
+
+        let centers: Vec<(usize, usize)> = zone_centers.iter().map(|c| map.get_closest_pathable(*c)).collect();
+        let count = centers.len();
+        let mut stale: Vec<Vec<usize>> = vec![Vec::new(); count];
+
+        for i in 0..count {
+            for j in i + 1..count {
+                if centers[i] != self.centers[i] || centers[j] != self.centers[j] || self.is_stale(map, &rects, i, j) {
+                    stale[i].push(j);
+                }
+            }
+        }
+
+        self.centers = centers;
+        for (i, others) in stale.iter().enumerate() {
+            if !others.is_empty() {
+                self.calculate_from(&map.map, i, others, map_type);
+            }
+        }
+    }
+
+    /// Whether the changed rectangles can have changed the distance between the zones.
+    /// Blocks that touch the path can make it longer, pathable cells can make a shorter path
+    /// and connect zones that weren't connected before.
+    fn is_stale(&self, map: &PathFind, rects: &[Rectangle], i: usize, j: usize) -> bool {
+        let start = self.centers[i];
+        let end = self.centers[j];
+
+        let distance = match self.distances[i][j] {
+            Some(distance) => distance,
+            None => return map.is_reachable(point2_f32(start), point2_f32(end)),
+        };
+
+        rects.iter().any(|rect| {
+                        self.paths[i][j].iter().any(|cell| blocked_next_to(&map.map, rect, *cell))
+                        || cells(rect).any(|cell| {
+                                          map.map[cell.0][cell.1] > 0
+                                          && octile_distance_f32(start, cell) + octile_distance_f32(cell, end)
+                                             < distance
+                                      })
+                    })
+    }
+
+    /// Calculates distances and paths from the zone to the other zones
+    fn calculate_from(&mut self, grid: &[Vec<usize>], source: usize, others: &[usize], map_type: u8) {
+        let field = DistanceField::new(grid, &[self.centers[source]], map_type);
+
+        for other in others {
+            let distance = field.distance(self.centers[*other]);
+            self.distances[source][*other] = distance;
+            self.distances[*other][source] = distance;
+
+            let (i, j) = (usize::min(source, *other), usize::max(source, *other));
+            self.paths[i][j] = field.path(grid, self.centers[*other]).unwrap_or_default();
+        }
+    }
+}
+
+#[pymethods]
+impl Map {
+    /// Walking distance between the centers of two zones, zones must be calculated first.
+    /// Distances that blocks may have changed since the last call are recalculated.
+    /// None when either zone doesn't exist or the zones aren't connected.
+    pub fn zone_distance(&mut self, a: i8, b: i8, map_type: u8) -> Option<f32> {
+        if a < 1 || b < 1 || a as usize > self.zone_centers.len() || b as usize > self.zone_centers.len() {
+            return None;
+        }
+
+        self.update_zone_distances(map_type);
+        let distances = &self.zone_distances.get(&map_type)?.distances;
+        distances[a as usize - 1][b as usize - 1]
+    }
+}
+
+impl Map {
+    /// Calculates distances between the zone centers for all map types
+    pub fn calculate_zone_distances(&mut self) {
+        self.zone_distances = HashMap::new();
+
+        for map_type in MAP_TYPES {
+            let distances = ZoneDistances::new(self.get_map(map_type), &self.zone_centers, map_type);
+            self.zone_distances.insert(map_type, distances);
+        }
+    }
+
+    /// Remembers the block rectangle to check the distances against on the next lookup.
+    /// Call before changing the pathing, so that changes can be told apart from blocks created again.
+    pub fn mark_zone_distances_changed(&mut self, center: (f32, f32), size: (usize, usize)) {
+        let rect = Rectangle::init_from_center(center, size, self.ground_pathing.width, self.ground_pathing.height);
+
+        for map_type in BLOCKED_MAP_TYPES {
+            if let Some(mut distances) = self.zone_distances.remove(&map_type) {
+                distances.mark_changed(&self.get_map(map_type).map, rect.clone());
+                self.zone_distances.insert(map_type, distances);
+            }
+        }
+    }
+
+    /// Recalculates distances that blocks may have changed
+    fn update_zone_distances(&mut self, map_type: u8) {
+        let distances = match self.zone_distances.remove(&map_type) {
+            Some(mut distances) => {
+                distances.update(self.get_map(map_type), &self.zone_centers, map_type);
+                distances
+            }
+            None => ZoneDistances::new(self.get_map(map_type), &self.zone_centers, map_type),
+        };
+        self.zone_distances.insert(map_type, distances);
+    }
+}
+
+/// Whether each cell of the rectangle is pathable
+fn pathable_cells(grid: &[Vec<usize>], rect: &Rectangle) -> Vec<bool> {
+    cells(rect).map(|cell| grid[cell.0][cell.1] > 0).collect()
+}
+
+/// Cells of the rectangle
+fn cells(rect: &Rectangle) -> impl Iterator<Item = (usize, usize)> + '_ {
+    (rect.x..rect.x_end).flat_map(move |x| (rect.y..rect.y_end).map(move |y| (x, y)))
+}
+
+/// Whether the cell or any of its neighbours is blocked inside the rectangle, blocked neighbours stop diagonal moves
+fn blocked_next_to(grid: &[Vec<usize>], rect: &Rectangle, cell: (usize, usize)) -> bool {
+    if cell.0 + 1 < rect.x || cell.0 > rect.x_end || cell.1 + 1 < rect.y || cell.1 > rect.y_end {
+        return false;
+    }
+
+    let x_range = usize::max(rect.x, cell.0.saturating_sub(1))..usize::min(rect.x_end, cell.0 + 2);
+    x_range.into_iter().any(|x| {
+                           let y_range =
+                               usize::max(rect.y, cell.1.saturating_sub(1))..usize::min(rect.y_end, cell.1 + 2);
+                           y_range.into_iter().any(|y| grid[x][y] == 0)
+                       })
+}
@@ -12,6 +12,7 @@ impl Map {
     pub fn calculate_zones(&mut self, sorted_base_locations: Vec<(f32, f32)>) {
         let mut index = 1;
         let copy_loc = sorted_base_locations.clone();
+        self.zone_centers = sorted_base_locations.iter().map(|p| (p.0 as usize, p.1 as usize)).collect();
 
         for pos in sorted_base_locations {
             let x = pos.0 as usize;
@@ -22,6 +23,8 @@ impl Map {
             flood_fill(self, x, y, target_height, index, pos, &copy_loc);
             index += 1;
         }
+
+        self.calculate_zone_distances();
     }
 
     pub fn draw_zones(&self) -> Vec<Vec<usize>> {
@@ -0,0 +1,253 @@
+use std::cell::{Ref, RefCell};
+use std::collections::{HashSet, VecDeque};
+
+use pyo3::prelude::*;
+
+use crate::helpers::round_point2;
+use crate::path_find::rectangle::Rectangle;
+use crate::path_find::PathFind;
+
+const NEIGHBOURS: [(i64, i64); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+/// Above this many changed rectangles labeling the whole grid again is faster than updating the labels
+const MAX_INCREMENTAL_UPDATES: usize = 4;
+
+/// Connected component label for every cell, 0 for cells that aren't pathable.
+/// Diagonal moves need both adjacent cells to be pathable, so cardinal neighbours are enough for connectivity.
+/// Changes are collected and the labels are only updated when they are next needed.
+#[derive(Clone)]
+pub struct Components {
+    labels: Vec<Vec<u32>>,
+    next_label: u32,
+    changed: Vec<Rectangle>,
+}
+
+impl Components {
+    pub fn new(grid: &[Vec<usize>]) -> Self {
+        let mut components = Components { labels: vec![vec![0; grid[0].len()]; grid.len()],
+                                          next_label: 1,
+                                          changed: Vec::new() };
+
+        for (x, column) in grid.iter().enumerate() {
+            for (y, value) in column.iter().enumerate() {
+                if *value > 0 && components.labels[x][y] == 0 {
+                    let label = components.new_label();
+                    components.fill(grid, (x, y), label, |_| true);
+                }
+            }
+        }
+
+        components
+    }
+
+    #[inline]
+    pub fn label(&self, cell: (usize, usize)) -> u32 { self.labels[cell.0][cell.1] }
+
+    fn new_label(&mut self) -> u32 {
+        let label = self.next_label;
+        self.next_label += 1;
+        label
+    }
+
+    /// Marks the labels in the rectangle as out of date after pathability of the cells has changed
+    pub fn mark_changed(&mut self, rect: &Rectangle) { self.changed.push(rect.clone()); }
+
+    /// Brings the labels up to date with the changes since the last refresh
+    pub fn refresh(&mut self, grid: &[Vec<usize>]) {
+        if self.changed.len() > MAX_INCREMENTAL_UPDATES {
+            *self = Components::new(grid);
+            return;
+        }
+
+        for rect in std::mem::take(&mut self.changed) {
+            self.update(grid, &rect);
+        }
+    }
+
+    /// Updates the labels after pathability of the cells in the rectangle has changed.
+    /// Only the components touching the rectangle are visited.
+    fn update(&mut self, grid: &[Vec<usize>], rect: &Rectangle) {
+        let mut split: HashSet<u32> = HashSet::new();
+        let mut opened: Vec<(usize, usize)> = Vec::new();
+
+        for (x, column) in grid.iter().enumerate().take(rect.x_end).skip(rect.x) {
+            for (y, value) in column.iter().enumerate().take(rect.y_end).skip(rect.y) {
+                let label = self.labels[x][y];
+                if *value == 0 && label != 0 {
+                    split.insert(label);
+                    self.labels[x][y] = 0;
+                } else if *value > 0 && label == 0 {
+                    opened.push((x, y));
+                }
+            }
+        }
+
+        if !split.is_empty() {
+            self.split_components(grid, rect, &split);
+        }
+
+        for cell in opened {
+            if self.labels[cell.0][cell.1] == 0 {
+                self.join_components(grid, cell);
+            }
+        }
+    }
+
+    /// Blocked cells may have cut the components in pieces, each piece touches the rectangle
+    fn split_components(&mut self, grid: &[Vec<usize>], rect: &Rectangle, split: &HashSet<u32>) {
+        let mut seeds: Vec<(usize, usize)> = Vec::new();
+
+        for x in rect.x.saturating_sub(1)..usize::min(grid.len(), rect.x_end + 1) {
+            for y in rect.y.saturating_sub(1)..usize::min(grid[0].len(), rect.y_end + 1) {
+                if split.contains(&self.labels[x][y]) {
+                    seeds.push((x, y));
+                }
+            }
+        }
+
+        for label in split {
+            let label_seeds: Vec<(usize, usize)> =
+                seeds.iter().filter(|s| self.labels[s.0][s.1] == *label).cloned().collect();
+            if label_seeds.is_empty() {
+                continue;
+            }
+
+            // The first piece keeps the label, stop as soon as it reaches all the other seeds
+            let mut remaining: HashSet<(usize, usize)> = label_seeds.iter().skip(1).cloned().collect();
+            self.search(grid, label_seeds[0], *label, &mut remaining);
+
+            for seed in remaining {
+                if self.labels[seed.0][seed.1] == *label {
+                    let new_label = self.new_label();
+                    self.fill(grid, seed, new_label, |l| l == *label);
+                }
+            }
+        }
+    }
+
+    /// Gives the newly pathable cell and every component it connects the same label
+    fn join_components(&mut self, grid: &[Vec<usize>], cell: (usize, usize)) {
+        let neighbour_label = self.neighbour_cells(grid, cell).map(|n| self.labels[n.0][n.1]).find(|l| *l != 0);
+        let label = match neighbour_label {
+            Some(label) => label,
+            None => self.new_label(),
+        };
+        self.labels[cell.0][cell.1] = label;
+        self.fill(grid, cell, label, |l| l != label);
+    }
+
+    /// Breadth first search through the label from start until all targets are reached.
+    /// Targets that weren't reached are left in the set.
+    fn search(&self, grid: &[Vec<usize>], start: (usize, usize), label: u32, targets: &mut HashSet<(usize, usize)>) {
+        let mut visited: HashSet<(usize, usize)> = HashSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(start);
+        queue.push_back(start);
+
+        while let Some(cell) = queue.pop_front() {
+            targets.remove(&cell);
+            if targets.is_empty() {
+                break;
+            }
+
+            for next in self.neighbour_cells(grid, cell) {
+                if self.labels[next.0][next.1] == label && visited.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    /// Sets the label to start and all pathable cells connected to it whose current label passes the filter
+    fn fill<F>(&mut self, grid: &[Vec<usize>], start: (usize, usize), label: u32, replace: F)
+        where F: Fn(u32) -> bool
+    {
+        let mut queue = VecDeque::new();
+        self.labels[start.0][start.1] = label;
+        queue.push_back(start);
+
+        while let Some(cell) = queue.pop_front() {
+            for next in self.neighbour_cells(grid, cell) {
+                let current = self.labels[next.0][next.1];
+                if current != label && replace(current) {
+                    self.labels[next.0][next.1] = label;
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    /// Pathable cardinal neighbours of the cell
+    fn neighbour_cells<'a>(&self,
+                           grid: &'a [Vec<usize>],
+                           cell: (usize, usize))
+                           -> impl Iterator<Item = (usize, usize)> + 'a {
+        NEIGHBOURS.iter().filter_map(move |(dx, dy)| {
+                             let x = cell.0 as i64 + dx;
+                             let y = cell.1 as i64 + dy;
+                             if x < 0 || y < 0 || x as usize >= grid.len() || y as usize >= grid[0].len() {
+                                 return None;
+                             }
+
+                             let next = (x as usize, y as usize);
+                             if grid[next.0][next.1] > 0 {
+                                 Some(next)
+                             } else {
+                                 None
+                             }
+                         })
+    }
+}
+
+#[pymethods]
+impl PathFind {
+    /// Whether a path exists between the points, blocked points are never reachable.
+    /// Uses connected component labels that are kept up to date by block changes.
+    pub fn is_reachable(&self, start: (f32, f32), end: (f32, f32)) -> bool {
+        let start = round_point2(start);
+        let end = round_point2(end);
+        if start.0 >= self.width || start.1 >= self.height || end.0 >= self.width || end.1 >= self.height {
+            return false;
+        }
+
+        let components = self.components();
+        let label = components.label(start);
+        label != 0 && label == components.label(end)
+    }
+
+    /// Connected component of the point, 0 for points that aren't pathable
+    pub fn component(&self, point: (f32, f32)) -> u32 {
+        let point = round_point2(point);
+        if point.0 >= self.width || point.1 >= self.height {
+            return 0;
+        }
+        self.components().label(point)
+    }
+
+    /// Labels connected components again, only needed after changing the map directly.
+    pub fn calculate_components(&mut self) { self.components = RefCell::new(Components::new(&self.map)); }
+}
+
+impl PathFind {
+    /// Marks the component labels out of date after the cells in the rectangle have changed
+    pub(crate) fn update_components(&mut self, rect: &Rectangle) { self.components.get_mut().mark_changed(rect); }
+
+    /// Component labels updated with the block changes made since they were last used
+    pub(crate) fn components(&self) -> Ref<'_, Components> {
+        self.components.borrow_mut().refresh(&self.map);
+        self.components.borrow()
+    }
+
+    /// Component of the cell on the map without any blocks
+    #[inline]
+    pub(crate) fn terrain_component(&self, cell: (usize, usize)) -> u32 { self.original_components.label(cell) }
+
+    /// Whether the pathable cells are in different components, meaning that no path can exist between them.
+    /// Paths can still leave a blocked start cell, so those are never considered disconnected.
+    #[inline]
+    pub(crate) fn is_disconnected(&self, start: (usize, usize), end: (usize, usize)) -> bool {
+        let components = self.components();
+        let start_label = components.label(start);
+        let end_label = components.label(end);
+        start_label != 0 && end_label != 0 && start_label != end_label
+    }
+}
@@ -0,0 +1,150 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use pyo3::prelude::*;
+
+use crate::path_find::pos::{self, NormalPosAPI, Pos, PositionAPI};
+use crate::path_find::PathFind;
+
+// Exposures that differ less than budget / EXPOSURE_BUCKETS are treated as equal when comparing labels
+const EXPOSURE_BUCKETS: usize = 32;
+// Most labels expanded in a single cell, later ones are longer and dropped
+const MAX_LABELS_PER_CELL: usize = 8;
+// Search gives up after expanding this many labels per cell of the map on average
+const MAX_EXPANSIONS_PER_CELL: usize = 4;
+
+/// Partial path for the label-setting search, distance and exposure are in pos::MULT units.
+struct Label {
+    position: Pos,
+    distance: usize,
+    exposure: usize,
+    parent: Option<usize>,
+}
+
+#[derive(Eq, PartialEq)]
+struct QueueItem {
+    estimate: usize,
+    exposure: usize,
+    label: usize,
+}
+
+impl Ord for QueueItem {
+    // Reversed for a min heap, ties are broken by lower exposure
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.cmp(&self.estimate).then_with(|| other.exposure.cmp(&self.exposure))
+    }
+}
+
+impl PartialOrd for QueueItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+#[pymethods]
+impl PathFind {
+    /// Finds the shortest path that never enters a cell with influence above max_influence
+    /// and keeps the total exposure under the exposure budget.
+    /// Influence is measured above normal influence and exposure is influence multiplied by distance travelled.
+    /// Exposures are compared in steps of 1/32 of the budget and the amount of searched paths is limited,
+    /// so a path that barely fits the budget may be missed.
+    /// Returns None when no such path is found.
+    pub fn find_path_constrained(&self,
+                                 start: (usize, usize),
+                                 end: (usize, usize),
+                                 max_influence: Option<usize>,
+                                 exposure_budget: Option<f32>)
+                                 -> Option<(Vec<(usize, usize)>, f32)> {
+        let start = self.get_closest_pathable(start);
+        let end = self.get_closest_pathable(end);
+        let max_influence = max_influence.unwrap_or(usize::MAX);
+        let budget = exposure_budget.map_or(usize::MAX, |b| (b * pos::MULTF32) as usize);
+        // Without a budget exposure doesn't matter and a single bucket makes this plain A*
+        let bucket_size = usize::max(1, budget / EXPOSURE_BUCKETS);
+        let max_expansions = self.width * self.height * MAX_EXPANSIONS_PER_CELL;
+        let mut expansions = 0;
+        let goal = Pos(end.0, end.1);
+        let api = NormalPosAPI();
+
+        if self.cell_excess_influence(start) > max_influence {
+            return None;
+        }
+
+        let mut labels = vec![Label { position: Pos(start.0, start.1),
+                                      distance: 0,
+                                      exposure: 0,
+                                      parent: None }];
+        // Non-dominated (distance, exposure bucket) pairs that have been expanded for each cell
+        let mut settled: Vec<Vec<(usize, usize)>> = vec![Vec::new(); self.width * self.height];
+        let mut queue = BinaryHeap::new();
+        queue.push(QueueItem { estimate: api.octile_distance(&labels[0].position, &goal),
+                               exposure: 0,
+                               label: 0 });
+
+        while let Some(item) = queue.pop() {
+            let label = &labels[item.label];
+            let (position, distance, exposure) = (label.position.clone(), label.distance, label.exposure);
+            let cell = position.0 * self.height + position.1;
+
+            if dominated(&settled[cell], distance, exposure / bucket_size) {
+                continue;
+            }
+            settled[cell].push((distance, exposure / bucket_size));
+
+            if position == goal {
+                return Some((self.trace_labels(&labels, item.label), distance as f32 / pos::MULTF32));
+            }
+
+            expansions += 1;
+            if expansions > max_expansions {
+                return None;
+            }
+
+            for (next, cost) in api.successors(&position, &self.map) {
+                let influence = self.cell_excess_influence((next.0, next.1));
+                if influence > max_influence {
+                    continue;
+                }
+
+                let next_exposure = exposure.saturating_add(influence * cost);
+                let next_distance = distance + cost;
+                if next_exposure > budget
+                   || dominated(&settled[next.0 * self.height + next.1], next_distance, next_exposure / bucket_size)
+                {
+                    continue;
+                }
+
+                let estimate = next_distance + api.octile_distance(&next, &goal);
+                labels.push(Label { position: next,
+                                    distance: next_distance,
+                                    exposure: next_exposure,
+                                    parent: Some(item.label) });
+                queue.push(QueueItem { estimate,
+                                       exposure: next_exposure,
+                                       label: labels.len() - 1 });
+            }
+        }
+
+        None
+    }
+}
+
+impl PathFind {
+    fn trace_labels(&self, labels: &[Label], last: usize) -> Vec<(usize, usize)> {
+        let mut path = Vec::new();
+        let mut current = Some(last);
+
+        while let Some(index) = current {
+            let label = &labels[index];
+            path.push((label.position.0, label.position.1));
+            current = label.parent;
+        }
+
+        path.reverse();
+        path
+    }
+}
+
+/// Later labels in a cell are never shorter, so a full cell accepts no more
+#[inline]
+fn dominated(settled: &[(usize, usize)], distance: usize, bucket: usize) -> bool {
+    settled.len() >= MAX_LABELS_PER_CELL || settled.iter().any(|(d, b)| *d <= distance && *b <= bucket)
+}
@@ -0,0 +1,384 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::path_find::PathFind;
+
+// Default amount of time steps each unit plans ahead while respecting the other units
+const DEFAULT_WINDOW: usize = 16;
+// Upper limit for replanning rounds as a multiple of the map size
+const MAX_ROUNDS_MULT: usize = 2;
+
+// Move from one cell to another, starting at the time step
+type TimedMove = ((usize, usize), (usize, usize), usize);
+// Goal and footprint of units that share step distances
+type HeuristicKey = ((usize, usize), Vec<(i64, i64)>);
+
+const MOVES: [(i64, i64); 9] = [(0, 0), (1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+/// Cells, and the moves between them, that are taken at each time step
+struct ReservationTable {
+    cells: HashMap<(usize, usize, usize), usize>,
+    moves: HashSet<TimedMove>,
+    width: usize,
+    height: usize,
+}
+
+impl ReservationTable {
+    fn new(width: usize, height: usize) -> Self {
+        ReservationTable { cells: HashMap::new(),
+                           moves: HashSet::new(),
+                           width,
+                           height }
+    }
+
+    fn reserve(&mut self, agent: usize, footprint: &[(i64, i64)], cell: (usize, usize), time: usize) {
+        for (x, y) in footprint_cells(footprint, cell, self.width, self.height) {
+            self.cells.insert((x, y, time), agent);
+        }
+    }
+
+    /// Frees the cells that the agent has reserved at the time
+    fn release(&mut self, agent: usize, footprint: &[(i64, i64)], cell: (usize, usize), time: usize) {
+        for (x, y) in footprint_cells(footprint, cell, self.width, self.height) {
+            if self.cells.get(&(x, y, time)) == Some(&agent) {
+                self.cells.remove(&(x, y, time));
+            }
+        }
+    }
+
+    fn reserve_move(&mut self, from: (usize, usize), to: (usize, usize), time: usize) {
+        if from != to {
+            self.moves.insert((from, to, time));
+        }
+    }
+
+    fn is_free(&self,
+               agent: usize,
+               footprint: &[(i64, i64)],
+               from: (usize, usize),
+               to: (usize, usize),
+               time: usize)
+               -> bool {
+        // Units swapping places would pass through each other
+        if self.moves.contains(&(to, from, time - 1)) {
+            return false;
+        }
+
+        footprint_cells(footprint, to, self.width, self.height).all(|(x, y)| match self.cells.get(&(x, y, time)) {
+                                                                   Some(owner) => *owner == agent,
+                                                                   None => true,
+                                                               })
+    }
+}
+
+struct Agent {
+    position: (usize, usize),
+    goal: (usize, usize),
+    footprint: Vec<(i64, i64)>,
+    heuristic: Vec<Vec<u32>>,
+    path: Vec<(usize, usize)>,
+}
+
+#[derive(Eq, PartialEq)]
+struct Node {
+    estimate: u32,
+    steps: u32,
+    index: usize,
+}
+
+impl Ord for Node {
+    // Reversed for a min heap, ties prefer nodes further along
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.cmp(&self.estimate).then_with(|| self.steps.cmp(&other.steps))
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+#[pymethods]
+impl PathFind {
+    /// Finds paths for a batch of units with windowed cooperative A* so that they don't collide.
+    /// Units reserve the cells they occupy at each time step and earlier units have priority.
+    /// Units that haven't planned yet are obstacles to the others, so a unit can't always reach its goal
+    /// but it never runs into another unit. Each returned path has one cell per time step,
+    /// so repeated cells mean the unit waits. Starts and goals must have the same length.
+    ///     window:
+    ///         time steps each unit plans ahead while respecting the other units, 16 by default
+    ///     radii:
+    ///         unit radius for each unit, units with radius 0.5 or less take a single cell.
+    ///         Larger units only move where all the cells they cover are pathable
+    ///         and their goal moves to the closest cell where they fit.
+    pub fn find_paths_cooperative(&self,
+                                  starts: Vec<(usize, usize)>,
+                                  goals: Vec<(usize, usize)>,
+                                  window: Option<usize>,
+                                  radii: Option<Vec<f32>>)
+                                  -> PyResult<Vec<Vec<(usize, usize)>>> {
+        if starts.len() != goals.len() {
+            return Err(PyValueError::new_err(format!("Got {} starts but {} goals", starts.len(), goals.len())));
+        }
+
+        let window = usize::max(2, window.unwrap_or(DEFAULT_WINDOW));
+        let radii = radii.unwrap_or_default();
+        // Units of the same size going to the same goal share the distances
+        let mut heuristics: HashMap<HeuristicKey, Vec<Vec<u32>>> = HashMap::new();
+        let mut agents: Vec<Agent> = Vec::with_capacity(starts.len());
+
+        for (i, (start, goal)) in starts.iter().zip(goals.iter()).enumerate() {
+            let start = self.get_closest_pathable(*start);
+            let footprint = footprint(radii.get(i).cloned().unwrap_or(0.5));
+            let goal = self.get_closest_pathable(*goal);
+            let goal = self.get_closest_pathable_filtered(goal, |c| self.fits(&footprint, c)).unwrap_or(goal);
+            let heuristic = heuristics.entry((goal, footprint.clone()))
+                                      .or_insert_with(|| self.step_distances(goal, &footprint))
+                                      .clone();
+
+            agents.push(Agent { position: start,
+                                goal,
+                                footprint,
+                                heuristic,
+                                path: vec![start] });
+        }
+
+        // Units move for half of the window before replanning
+        let step = window / 2;
+        let max_rounds = MAX_ROUNDS_MULT * (self.width + self.height) / step + 1;
+        let mut time = 0;
+
+        for _ in 0..max_rounds {
+            if agents.iter().all(|a| a.position == a.goal) {
+                break;
+            }
+
+            // Every unit stays where it is until it has planned, so that units planned before it go around it
+            // and waiting is always possible when no better plan is found
+            let mut table = ReservationTable::new(self.width, self.height);
+            for (i, agent) in agents.iter().enumerate() {
+                for t in 0..=window {
+                    table.reserve(i, &agent.footprint, agent.position, time + t);
+                }
+            }
+
+            let plans: Vec<Vec<(usize, usize)>> =
+                (0..agents.len()).map(|i| {
+                                     for t in 0..=window {
+                                         table.release(i, &agents[i].footprint, agents[i].position, time + t);
+                                     }
+                                     let plan = self.plan_window(&agents[i], i, &table, time, window);
+                                     for (t, cell) in plan.iter().enumerate() {
+                                         table.reserve(i, &agents[i].footprint, *cell, time + t);
+                                         if t > 0 {
+                                             table.reserve_move(plan[t - 1], *cell, time + t - 1);
+                                         }
+                                     }
+                                     plan
+                                 })
+                                 .collect();
+
+            for (agent, plan) in agents.iter_mut().zip(plans.iter()) {
+                agent.path.extend(plan.iter().skip(1).take(step));
+                agent.position = *agent.path.last().unwrap();
+            }
+            time += step;
+        }
+
+        Ok(agents.into_iter()
+                 .map(|mut agent| {
+                     // Waiting at the end isn't part of the path
+                     let end = *agent.path.last().unwrap();
+                     while agent.path.len() > 1 && agent.path[agent.path.len() - 2] == end {
+                         agent.path.pop();
+                     }
+                     agent.path
+                 })
+                 .collect())
+    }
+}
+
+impl PathFind {
+    /// Space-time A* from the agent position for window time steps, cell for each time step starting from now.
+    /// When neither the goal nor the end of the window can be reached, the plan ends at the cell closest
+    /// to the goal where the agent can wait until the end of the window.
+    fn plan_window(&self,
+                   agent: &Agent,
+                   index: usize,
+                   table: &ReservationTable,
+                   time: usize,
+                   window: usize)
+                   -> Vec<(usize, usize)> {
+        // Nodes as (cell, steps from now, parent)
+        let mut nodes: Vec<((usize, usize), u32, Option<usize>)> = vec![(agent.position, 0, None)];
+        let mut visited: HashSet<((usize, usize), u32)> = HashSet::new();
+        let mut queue = BinaryHeap::new();
+        let h = |cell: (usize, usize)| agent.heuristic[cell.0][cell.1];
+        queue.push(Node { estimate: h(agent.position),
+                          steps: 0,
+                          index: 0 });
+        // Other units leave the start free for the whole window
+        let mut best_end = 0;
+        let mut best_h = h(agent.position);
+
+        while let Some(Node { steps,
+                              index: node,
+                              .. }) = queue.pop()
+        {
+            let (cell, _, _) = nodes[node];
+            if !visited.insert((cell, steps)) {
+                continue;
+            }
+
+            if steps as usize == window
+               || (cell == agent.goal && self.can_wait(agent, index, table, cell, time, steps, window))
+            {
+                best_end = node;
+                break;
+            }
+            if h(cell) < best_h && self.can_wait(agent, index, table, cell, time, steps, window) {
+                best_end = node;
+                best_h = h(cell);
+            }
+
+            for (dx, dy) in MOVES.iter() {
+                let x = cell.0 as i64 + dx;
+                let y = cell.1 as i64 + dy;
+                if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+                    continue;
+                }
+
+                let next = (x as usize, y as usize);
+                if agent.heuristic[next.0][next.1] == u32::MAX || !self.can_move(&agent.footprint, cell, next) {
+                    continue;
+                }
+                if !table.is_free(index, &agent.footprint, cell, next, time + steps as usize + 1) {
+                    continue;
+                }
+
+                nodes.push((next, steps + 1, Some(node)));
+                queue.push(Node { estimate: steps + 1 + h(next),
+                                  steps: steps + 1,
+                                  index: nodes.len() - 1 });
+            }
+        }
+
+        let mut plan = Vec::new();
+        let mut current = Some(best_end);
+        while let Some(node) = current {
+            plan.push(nodes[node].0);
+            current = nodes[node].2;
+        }
+        plan.reverse();
+
+        // Stay at the end of the plan for the rest of the window
+        let last = *plan.last().unwrap();
+        while plan.len() <= window {
+            plan.push(last);
+        }
+        plan
+    }
+
+    /// Whether the agent can stay at the cell until the end of the window
+    #[allow(clippy::too_many_arguments)]
+    fn can_wait(&self,
+                agent: &Agent,
+                index: usize,
+                table: &ReservationTable,
+                cell: (usize, usize),
+                time: usize,
+                steps: u32,
+                window: usize)
+                -> bool {
+        (steps as usize + 1..=window).all(|t| table.is_free(index, &agent.footprint, cell, cell, time + t))
+    }
+
+    /// Smallest amount of moves for the footprint to the goal from every cell, u32::MAX if unreachable
+    fn step_distances(&self, goal: (usize, usize), footprint: &[(i64, i64)]) -> Vec<Vec<u32>> {
+        let mut distances = vec![vec![u32::MAX; self.height]; self.width];
+        let mut queue = VecDeque::new();
+        distances[goal.0][goal.1] = 0;
+        queue.push_back(goal);
+
+        while let Some(cell) = queue.pop_front() {
+            let distance = distances[cell.0][cell.1];
+            for (dx, dy) in MOVES.iter().skip(1) {
+                let x = cell.0 as i64 + dx;
+                let y = cell.1 as i64 + dy;
+                if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+                    continue;
+                }
+
+                let next = (x as usize, y as usize);
+                if distances[next.0][next.1] != u32::MAX || !self.can_move(footprint, next, cell) {
+                    continue;
+                }
+
+                distances[next.0][next.1] = distance + 1;
+                queue.push_back(next);
+            }
+        }
+
+        distances
+    }
+
+    /// Whether all cells covered by the footprint at the cell are inside the map and pathable
+    fn fits(&self, footprint: &[(i64, i64)], cell: (usize, usize)) -> bool {
+        footprint.iter().all(|(dx, dy)| {
+                            let x = cell.0 as i64 + dx;
+                            let y = cell.1 as i64 + dy;
+                            x >= 0
+                            && y >= 0
+                            && (x as usize) < self.width
+                            && (y as usize) < self.height
+                            && self.map[x as usize][y as usize] > 0
+                        })
+    }
+
+    /// Whether the footprint fits at the next cell, diagonal moves can't cut corners
+    fn can_move(&self, footprint: &[(i64, i64)], cell: (usize, usize), next: (usize, usize)) -> bool {
+        if !self.fits(footprint, next) {
+            return false;
+        }
+
+        cell.0 == next.0
+        || cell.1 == next.1
+        || (self.fits(footprint, (next.0, cell.1)) && self.fits(footprint, (cell.0, next.1)))
+    }
+}
+
+/// Cell offsets covered by a unit of the radius
+fn footprint(radius: f32) -> Vec<(i64, i64)> {
+    let reach = radius + 0.5;
+    let r = reach.floor() as i64;
+    let mut offsets = Vec::new();
+
+    for dx in -r..=r {
+        for dy in -r..=r {
+            if ((dx * dx + dy * dy) as f32) < reach * reach || (dx == 0 && dy == 0) {
+                offsets.push((dx, dy));
+            }
+        }
+    }
+
+    offsets
+}
+
+/// Cells covered by the footprint at the cell that are inside the map
+fn footprint_cells(footprint: &[(i64, i64)],
+                   cell: (usize, usize),
+                   width: usize,
+                   height: usize)
+                   -> impl Iterator<Item = (usize, usize)> + '_ {
+    footprint.iter().filter_map(move |(dx, dy)| {
+                        let x = cell.0 as i64 + dx;
+                        let y = cell.1 as i64 + dy;
+                        if x < 0 || y < 0 || x as usize >= width || y as usize >= height {
+                            None
+                        } else {
+                            Some((x as usize, y as usize))
+                        }
+                    })
+}
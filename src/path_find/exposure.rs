@@ -0,0 +1,110 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::helpers::round_point2;
+use crate::path_find::{euclidean_distance, PathFind};
+
+// Maximum distance between influence samples along a segment
+const SAMPLE_STEP: f32 = 0.5;
+
+/// Influence integrated along a path. Influence is measured above the normal influence of the map.
+#[pyclass]
+#[derive(Clone, Debug, Default)]
+pub struct PathExposure {
+    pub total: f32,
+    pub peak: usize,
+    pub time_above: f32,
+    pub worst_segment: Option<usize>,
+}
+
+#[pymethods]
+impl PathExposure {
+    /// Sum of influence multiplied by the time spent in it
+    #[getter(total)]
+    fn get_total(&self) -> f32 { self.total }
+
+    /// Highest influence on the path
+    #[getter(peak)]
+    fn get_peak(&self) -> usize { self.peak }
+
+    /// Time spent in influence above the threshold
+    #[getter(time_above)]
+    fn get_time_above(&self) -> f32 { self.time_above }
+
+    /// Index of the segment starting from path[index] with the highest exposure, None when there is no exposure
+    #[getter(worst_segment)]
+    fn get_worst_segment(&self) -> Option<usize> { self.worst_segment }
+}
+
+#[pymethods]
+impl PathFind {
+    /// Integrates influence along the path of cells or waypoints for a unit moving at unit_speed.
+    /// Threshold is influence above normal influence and defaults to 0. Unit speed must be above 0.
+    pub fn path_exposure(&self,
+                         path: Vec<(f32, f32)>,
+                         unit_speed: f32,
+                         threshold: Option<usize>)
+                         -> PyResult<PathExposure> {
+        if unit_speed.is_nan() || unit_speed <= 0.0 {
+            return Err(PyValueError::new_err(format!("Unit speed must be above 0, got {}", unit_speed)));
+        }
+
+        Ok(self.exposure_along(&path, unit_speed, threshold.unwrap_or(0)))
+    }
+}
+
+impl PathFind {
+    /// Same as `path_exposure` without validating the unit speed
+    pub(crate) fn exposure_along(&self, path: &[(f32, f32)], unit_speed: f32, threshold: usize) -> PathExposure {
+        let mut exposure = PathExposure::default();
+        let mut worst = 0.0;
+
+        if let Some(first) = path.first() {
+            exposure.peak = self.excess_influence(*first);
+        }
+
+        for (index, segment) in path.windows(2).enumerate() {
+            let (start, end) = (segment[0], segment[1]);
+            let length = euclidean_distance(start, end);
+            let samples = usize::max(1, (length / SAMPLE_STEP).ceil() as usize);
+            let step_time = length / samples as f32 / unit_speed;
+            let mut segment_total = 0.0;
+
+            // Sample the middle of each step so that shared waypoints aren't counted twice
+            for i in 0..samples {
+                let t = (i as f32 + 0.5) / samples as f32;
+                let point = (start.0 + (end.0 - start.0) * t, start.1 + (end.1 - start.1) * t);
+                let influence = self.excess_influence(point);
+
+                segment_total += influence as f32 * step_time;
+                exposure.peak = usize::max(exposure.peak, influence);
+                if influence > threshold {
+                    exposure.time_above += step_time;
+                }
+            }
+
+            exposure.total += segment_total;
+            if segment_total > worst {
+                worst = segment_total;
+                exposure.worst_segment = Some(index);
+            }
+        }
+
+        exposure
+    }
+
+    /// Influence above normal influence, blocked or outside cells have none
+    fn excess_influence(&self, point: (f32, f32)) -> usize {
+        let (x, y) = round_point2(point);
+        if x >= self.width || y >= self.height {
+            return 0;
+        }
+
+        self.cell_excess_influence((x, y))
+    }
+
+    #[inline]
+    pub(crate) fn cell_excess_influence(&self, position: (usize, usize)) -> usize {
+        self.map[position.0][position.1].saturating_sub(self.normal_influence)
+    }
+}
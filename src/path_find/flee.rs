@@ -0,0 +1,121 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use pyo3::prelude::*;
+
+use crate::helpers::round_point2;
+use crate::path_find::pos::{self, InfluencedPosAPI, Pos, PositionAPI};
+use crate::path_find::{euclidean_distance, octile_distance, PathFind};
+
+// Radius of the square used to measure how open the area around a cell is
+const OPENNESS_RADIUS: usize = 3;
+// Score penalty for a cell with no open space around it, in cells of distance gained
+const CORNER_PENALTY: f32 = 6.0;
+
+/// Parent, influence cost and walking distance of the route to each reached cell
+type Routes = HashMap<(usize, usize), ((usize, usize), usize, usize)>;
+
+#[pymethods]
+impl PathFind {
+    /// Finds a path within walking distance that gets away from the threats.
+    /// Destinations are scored by distance gained from the closest threat,
+    /// penalized by influence on the way and by corners or dead ends around the destination.
+    /// Returns the path and its walking distance, which is at most the given distance.
+    pub fn find_flee_path(&self,
+                          start: (f32, f32),
+                          threats: Vec<(f32, f32)>,
+                          distance: f32)
+                          -> (Vec<(usize, usize)>, f32) {
+        let start = self.get_closest_pathable(round_point2(start));
+        if threats.is_empty() {
+            return (vec![start], 0.0);
+        }
+
+        let parents = self.flee_routes(start, (distance * pos::MULTF32) as usize);
+        let start_threat_distance = closest_threat_distance(&threats, start);
+        let influence_mult = 1.0 / (pos::MULTF32 * self.normal_influence as f32);
+        let mut best: Option<((usize, usize), f32)> = None;
+
+        for (cell, (_, cost, walked)) in parents.iter() {
+            let cell = *cell;
+            let walk_distance = *walked as f32 / pos::MULTF32;
+            let gained = closest_threat_distance(&threats, cell) - start_threat_distance;
+            // Cost of the route on top of plain distance comes from influence
+            let influence_penalty = f32::max(0.0, *cost as f32 * influence_mult - walk_distance);
+            let corner_penalty = (1.0 - self.openness(cell)) * CORNER_PENALTY;
+            let score = gained - influence_penalty - corner_penalty;
+
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((cell, score));
+            }
+        }
+
+        match best {
+            Some((target, _)) => {
+                let walked = parents[&target].2 as f32 / pos::MULTF32;
+                let mut path = vec![target];
+                while let Some((parent, _, _)) = parents.get(path.last().unwrap()) {
+                    path.push(*parent);
+                }
+                path.reverse();
+                (path, walked)
+            }
+            None => (vec![start], 0.0),
+        }
+    }
+}
+
+impl PathFind {
+    /// Cheapest routes by influence from the start that walk at most max_walk, distances multiplied by pos::MULT.
+    fn flee_routes(&self, start: (usize, usize), max_walk: usize) -> Routes {
+        let api = InfluencedPosAPI { normal_influence: self.normal_influence };
+        let mut costs: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut parents: Routes = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        costs.insert(start, 0);
+        queue.push(Reverse((0, 0, start)));
+
+        while let Some(Reverse((cost, walked, cell))) = queue.pop() {
+            if cost > costs[&cell] {
+                continue;
+            }
+
+            for (next, step_cost) in api.successors(&Pos(cell.0, cell.1), &self.map) {
+                let next_cell = (next.0, next.1);
+                let next_walked = walked + octile_distance(cell, next_cell);
+                let next_cost = cost + step_cost;
+                if next_walked > max_walk
+                   || next_cell == start
+                   || costs.get(&next_cell).is_some_and(|c| *c <= next_cost)
+                {
+                    continue;
+                }
+
+                costs.insert(next_cell, next_cost);
+                parents.insert(next_cell, (cell, next_cost, next_walked));
+                queue.push(Reverse((next_cost, next_walked, next_cell)));
+            }
+        }
+
+        parents
+    }
+
+    /// Share of pathable cells in the square around the cell, corners and narrow dead ends have low openness
+    fn openness(&self, cell: (usize, usize)) -> f32 {
+        let x_start = cell.0.saturating_sub(OPENNESS_RADIUS);
+        let y_start = cell.1.saturating_sub(OPENNESS_RADIUS);
+        let x_end = usize::min(self.width, cell.0 + OPENNESS_RADIUS + 1);
+        let y_end = usize::min(self.height, cell.1 + OPENNESS_RADIUS + 1);
+        let side = 2 * OPENNESS_RADIUS + 1;
+
+        let pathable = self.map[x_start..x_end].iter()
+                                               .map(|column| column[y_start..y_end].iter().filter(|v| **v > 0).count())
+                                               .sum::<usize>();
+        pathable as f32 / (side * side) as f32
+    }
+}
+
+fn closest_threat_distance(threats: &[(f32, f32)], cell: (usize, usize)) -> f32 {
+    let point = (cell.0 as f32, cell.1 as f32);
+    threats.iter().map(|t| euclidean_distance(*t, point)).fold(f32::MAX, f32::min)
+}
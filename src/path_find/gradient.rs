@@ -0,0 +1,58 @@
+use pyo3::prelude::*;
+
+use crate::path_find::PathFind;
+
+// Gradients weaker than this are considered flat
+const MIN_GRADIENT: f32 = 1e-4;
+
+#[pymethods]
+impl PathFind {
+    /// Unit vector pointing towards decreasing influence, (0, 0) when influence is flat.
+    /// Influence differences of pathable cells within radius are weighted by closeness to the position.
+    pub fn influence_gradient(&self, position: (f32, f32), radius: f32) -> (f32, f32) {
+        let r = radius.ceil() as i64;
+        let cx = position.0.round() as i64;
+        let cy = position.1.round() as i64;
+        let mut cells: Vec<(f32, f32, f32, f32)> = Vec::new();
+
+        for x in i64::max(0, cx - r)..i64::min(self.width as i64, cx + r + 1) {
+            for y in i64::max(0, cy - r)..i64::min(self.height as i64, cy + r + 1) {
+                let value = self.map[x as usize][y as usize];
+                if value == 0 {
+                    continue;
+                }
+
+                let dx = x as f32 - position.0;
+                let dy = y as f32 - position.1;
+                let d = (dx * dx + dy * dy).sqrt();
+                if d > radius {
+                    continue;
+                }
+
+                cells.push((dx, dy, value as f32, 1.0 - d / (radius + 1.0)));
+            }
+        }
+
+        let weight_sum: f32 = cells.iter().map(|c| c.3).sum();
+        if weight_sum == 0.0 {
+            return (0.0, 0.0);
+        }
+
+        let mean = cells.iter().map(|c| c.2 * c.3).sum::<f32>() / weight_sum;
+        let mut gx = 0.0;
+        let mut gy = 0.0;
+
+        for (dx, dy, value, weight) in cells.iter() {
+            let difference = (mean - value) * weight;
+            gx += difference * dx;
+            gy += difference * dy;
+        }
+
+        let length = (gx * gx + gy * gy).sqrt();
+        if length < MIN_GRADIENT {
+            return (0.0, 0.0);
+        }
+
+        (gx / length, gy / length)
+    }
+}
@@ -1,17 +1,25 @@
 use pathfinding::prelude::{astar, dijkstra_all, dijkstra_partial};
 use pyo3::prelude::*;
+use std::cell::RefCell;
 
 use crate::helpers::point2_f32;
 use crate::mapping::vision::VisionMap;
 use crate::path_find::pos::Pos;
-use crate::path_find::pos::{InfluencedPosAPI, InvertPosAPI, NormalPosAPI, PositionAPI};
+use crate::path_find::pos::{InfluencedPosAPI, InvertPosAPI, NormalPosAPI, PositionAPI, UnseenPosAPI};
 use crate::path_find::pos_large::{InfluencedPosLargeAPI, PosLargeAPI};
 
 mod angles;
+pub mod components;
+pub mod constrained;
+pub mod cooperative;
+pub mod exposure;
+pub mod flee;
+pub mod gradient;
 pub mod pos;
 mod pos_large;
 pub mod rectangle;
 mod search_grid;
+pub mod shapes;
 
 #[pyclass]
 pub struct PathFind {
@@ -19,9 +27,11 @@ pub struct PathFind {
     original_map: Vec<Vec<usize>>,
     pub width: usize,
     pub height: usize,
-    normal_influence: usize,
+    pub normal_influence: usize,
     auto_correct: bool,
     free_finder: search_grid::FreeFinder,
+    components: RefCell<components::Components>,
+    original_components: components::Components,
 }
 
 #[inline]
@@ -69,6 +79,7 @@ impl PathFind {
         let normal_influence: usize = 1;
         let auto_correct: bool = true;
         let free_finder = search_grid::FreeFinder::new();
+        let components = components::Components::new(&map);
 
         PathFind { map,
                    original_map,
@@ -76,7 +87,9 @@ impl PathFind {
                    height,
                    normal_influence,
                    auto_correct,
-                   free_finder }
+                   free_finder,
+                   original_components: components.clone(),
+                   components: RefCell::new(components) }
     }
     // Removes multiple blocks on the grid and makes it pathable
     // center = center of block
@@ -89,9 +102,27 @@ impl PathFind {
                     self.map[x][y] = self.normal_influence;
                 }
             }
+            self.update_components(&rect);
         }
     }
 
+    /// Sets the cells under the block that are pathable on the original map to normal influence,
+    /// cells that any of the covering blocks still overlaps stay blocked.
+    pub fn restore_block_rust(&mut self, center: (f32, f32), size: (usize, usize), covering: &[rectangle::Rectangle]) {
+        let rect = rectangle::Rectangle::init_from_center(center, size, self.width, self.height);
+
+        for x in rect.x..rect.x_end {
+            for y in rect.y..rect.y_end {
+                self.map[x][y] = if self.original_map[x][y] == 0 || covering.iter().any(|r| r.contains((x, y))) {
+                    0
+                } else {
+                    self.normal_influence
+                };
+            }
+        }
+        self.update_components(&rect);
+    }
+
     // Creates a block on the grid that is not pathable
     // center = center of building
     pub fn create_blocks_rust(&mut self, centers: &[(f32, f32)], size: (usize, usize)) {
@@ -103,8 +134,52 @@ impl PathFind {
                     self.map[x][y] = 0;
                 }
             }
+            self.update_components(&rect);
+        }
+    }
+
+    /// Finds a path where cells seen or detected in the vision map cost extra, uses influence pathfinding.
+    pub fn find_path_unseen(&self,
+                            vision_map: &VisionMap,
+                            start: (usize, usize),
+                            end: (usize, usize),
+                            seen_cost: usize,
+                            detection_cost: usize,
+                            possible_heuristic: Option<u8>)
+                            -> (Vec<(usize, usize)>, f32) {
+        let corrected_start = self.get_closest_pathable(start);
+        let corrected_end = self.get_closest_pathable(end);
+
+        if self.is_disconnected(corrected_start, corrected_end) {
+            return (Vec::<(usize, usize)>::new(), 0.0);
+        }
+
+        let grid: &Vec<Vec<usize>> = &self.map;
+        let api = UnseenPosAPI { influence: InfluencedPosAPI { normal_influence: self.normal_influence },
+                                 vision_map,
+                                 seen_cost,
+                                 detection_cost };
+        let start: Pos = Pos(corrected_start.0, corrected_start.1);
+        let goal: Pos = Pos(corrected_end.0, corrected_end.1);
+
+        let result = match possible_heuristic.unwrap_or(0) {
+            0 => astar(&start, |p| api.successors(p, grid), |p| api.manhattan_distance(p, &goal), |p| *p == goal),
+            1 => astar(&start, |p| api.successors(p, grid), |p| api.octile_distance(p, &goal), |p| *p == goal),
+            _ => astar(&start, |p| api.successors(p, grid), |p| api.euclidean_distance(p, &goal), |p| *p == goal),
+        };
+
+        match result {
+            None => (Vec::<(usize, usize)>::new(), 0.0),
+            Some((path, cost)) => (path.iter().map(|pos| (pos.0, pos.1)).collect(), (cost as f32) / pos::MULTF32),
         }
     }
+
+    /// Closest pathable cell to the start that passes the filter, None if there is none nearby.
+    pub(crate) fn get_closest_pathable_filtered<F>(&self, start: (usize, usize), accept: F) -> Option<(usize, usize)>
+        where F: Fn((usize, usize)) -> bool
+    {
+        self.free_finder.find_free_filtered(start, &self.map, self.width, self.height, accept)
+    }
 }
 
 #[pymethods]
@@ -117,6 +192,7 @@ impl PathFind {
         let normal_influence: usize = 1;
         let auto_correct: bool = true;
         let free_finder = search_grid::FreeFinder::new();
+        let components = components::Components::new(&map);
 
         PathFind { map,
                    original_map,
@@ -124,7 +200,9 @@ impl PathFind {
                    height,
                    normal_influence,
                    auto_correct,
-                   free_finder }
+                   free_finder,
+                   original_components: components.clone(),
+                   components: RefCell::new(components) }
     }
 
     // object.width
@@ -147,6 +225,7 @@ impl PathFind {
     #[setter(map)]
     fn set_map(&mut self, value: Vec<Vec<usize>>) -> PyResult<()> {
         self.map = value;
+        self.calculate_components();
         Ok(())
     }
 
@@ -162,11 +241,14 @@ impl PathFind {
     }
 
     pub fn reset(&mut self) -> PyResult<()> {
-        self.map = self.original_map.clone();
+        self.reset_void();
         Ok(())
     }
 
-    pub fn reset_void(&mut self) { self.map = self.original_map.clone(); }
+    pub fn reset_void(&mut self) {
+        self.map = self.original_map.clone();
+        self.components = RefCell::new(self.original_components.clone());
+    }
 
     // Creates a block on the grid that is not pathable
     // center = center of building
@@ -178,6 +260,7 @@ impl PathFind {
                 self.map[x][y] = 0;
             }
         }
+        self.update_components(&rect);
     }
 
     // Creates a block on the grid that is not pathable
@@ -191,6 +274,7 @@ impl PathFind {
                     self.map[x][y] = 0;
                 }
             }
+            self.update_components(&rect);
         }
     }
 
@@ -204,6 +288,7 @@ impl PathFind {
                 self.map[x][y] = self.normal_influence;
             }
         }
+        self.update_components(&rect);
     }
 
     pub fn normalize_influence(&mut self, value: usize) {
@@ -435,6 +520,12 @@ impl PathFind {
                         -> (Vec<(usize, usize)>, f32) {
         let corrected_start = self.get_closest_pathable(given_start);
         let corrected_end = self.get_closest_pathable(given_end);
+
+        // No path can reach the end exactly, but a path to within distance of it can still exist
+        if possible_distance_from_target.is_none() && self.is_disconnected(corrected_start, corrected_end) {
+            return (Vec::<(usize, usize)>::new(), 0.0);
+        }
+
         let grid: &Vec<Vec<usize>> = &self.map;
         let api: &dyn PositionAPI;
         let normal_api = NormalPosAPI();
@@ -625,7 +716,7 @@ impl PathFind {
     }
 
     #[inline]
-    fn get_closest_pathable(&self, start: (usize, usize)) -> (usize, usize) {
+    pub(crate) fn get_closest_pathable(&self, start: (usize, usize)) -> (usize, usize) {
         if !self.auto_correct || self.map[start.0][start.1] > 0 {
             start
         } else {
@@ -731,6 +822,8 @@ impl PathFind {
         destination_collection
     }
 
+    /// Adds seen_value to cells that are only seen and detection_value to cells that are detected,
+    /// whether they are also seen or not.
     pub fn add_influence_to_map_by_vision(&mut self,
                                           vision_map: &VisionMap,
                                           seen_value: usize,
@@ -741,10 +834,32 @@ impl PathFind {
                 if status == 1 {
                     self.add_influence_spot((x, y), seen_value);
                 }
-                if status == 2 {
+                if status == 2 || status == 3 {
                     self.add_influence_spot((x, y), detection_value);
                 }
             }
         }
     }
+
+    /// Adds influence to cells that haven't been seen recently.
+    /// Influence grows linearly with staleness and reaches max value after full_staleness loops.
+    pub fn add_influence_to_map_by_memory(&mut self,
+                                          vision_map: &VisionMap,
+                                          game_loop: u32,
+                                          max_value: f32,
+                                          full_staleness: u32) {
+        let mult = 1.0 / full_staleness.max(1) as f32;
+
+        for x in 0..self.width {
+            for y in 0..self.height {
+                if self.map[x][y] == 0 {
+                    continue;
+                }
+
+                let staleness = vision_map.staleness((x as f32, y as f32), game_loop) as f32;
+                let value = max_value * f32::min(1.0, staleness * mult);
+                self.add_influence_spot((x, y), value as usize);
+            }
+        }
+    }
 }
@@ -1,4 +1,6 @@
 use arrayvec::ArrayVec;
+
+use crate::mapping::vision::VisionMap;
 //static SQRT2: f32 = 1.4142135623730950488016887242097;
 pub static SQRT2: usize = 14142;
 pub static MULT: usize = 10000;
@@ -261,6 +263,59 @@ impl PositionAPI for InfluencedPosAPI {
     }
 }
 
+/// Influenced movement where cells seen or detected in the vision map cost extra
+pub struct UnseenPosAPI<'a> {
+    pub influence: InfluencedPosAPI,
+    pub vision_map: &'a VisionMap,
+    pub seen_cost: usize,
+    pub detection_cost: usize,
+}
+
+impl UnseenPosAPI<'_> {
+    /// Extra influence for entering the cell
+    #[inline]
+    fn vision_cost(&self, pos: &Pos) -> usize {
+        match self.vision_map.vision_status((pos.0 as f32, pos.1 as f32)) {
+            1 => self.seen_cost,
+            2 | 3 => self.detection_cost,
+            _ => 0,
+        }
+    }
+}
+
+impl PositionAPI for UnseenPosAPI<'_> {
+    #[inline]
+    fn manhattan_distance(&self, start: &Pos, end: &Pos) -> usize { self.influence.manhattan_distance(start, end) }
+
+    #[inline]
+    fn euclidean_distance(&self, start: &Pos, end: &Pos) -> usize { self.influence.euclidean_distance(start, end) }
+
+    #[inline]
+    fn octile_distance(&self, start: &Pos, end: &Pos) -> usize { self.influence.octile_distance(start, end) }
+
+    #[inline]
+    fn successors(&self, pos: &Pos, grid: &[Vec<usize>]) -> ArrayVec<(Pos, usize), 8> {
+        self.successors_within(pos, grid, ((0, 0), (grid.len(), grid[0].len())))
+    }
+
+    #[inline]
+    fn successors_within(&self,
+                         pos: &Pos,
+                         grid: &[Vec<usize>],
+                         window: ((usize, usize), (usize, usize)))
+                         -> ArrayVec<(Pos, usize), 8> {
+        let mut arr = self.influence.successors_within(pos, grid, window);
+
+        for (next, cost) in arr.iter_mut() {
+            // Cost is the cell value times MULT or SQRT2 depending on the direction
+            let step = *cost / grid[next.0][next.1];
+            *cost += self.vision_cost(next) * step;
+        }
+
+        arr
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct InvertPosAPI();
 
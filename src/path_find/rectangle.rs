@@ -42,4 +42,14 @@ impl Rectangle {
                     x_end,
                     y_end }
     }
+
+    #[inline]
+    pub fn contains(&self, point: (usize, usize)) -> bool {
+        point.0 >= self.x && point.0 < self.x_end && point.1 >= self.y && point.1 < self.y_end
+    }
+
+    #[inline]
+    pub fn overlaps(&self, other: &Rectangle) -> bool {
+        self.x < other.x_end && other.x < self.x_end && self.y < other.y_end && other.y < self.y_end
+    }
 }
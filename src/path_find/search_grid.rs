@@ -28,6 +28,24 @@ impl FreeFinder {
 
         result
     }
+
+    /// Closest pathable cell to the lookup that passes the filter, the lookup itself included.
+    /// None if there is no such cell within the search grid.
+    pub fn find_free_filtered<F>(&self,
+                                 lookup: (usize, usize),
+                                 map: &[Vec<usize>],
+                                 width: usize,
+                                 height: usize,
+                                 accept: F)
+                                 -> Option<(usize, usize)>
+        where F: Fn((usize, usize)) -> bool
+    {
+        std::iter::once(&(0, 0)).chain(self.closest_grid.iter())
+                                .map(|offset| (lookup.0 as i64 + offset.0, lookup.1 as i64 + offset.1))
+                                .filter(|c| c.0 >= 0 && c.1 >= 0 && (c.0 as usize) < width && (c.1 as usize) < height)
+                                .map(|c| (c.0 as usize, c.1 as usize))
+                                .find(|c| map[c.0][c.1] > 0 && accept(*c))
+    }
 }
 
 /// ## Search order as follows:
@@ -0,0 +1,233 @@
+use pyo3::prelude::*;
+
+use crate::path_find::rectangle::Rectangle;
+use crate::path_find::{euclidean_distance, PathFind};
+
+#[derive(Clone, Debug)]
+enum Shape {
+    /// Segment with rounded ends
+    Capsule {
+        start: (f32, f32),
+        end: (f32, f32),
+        radius: f32,
+    },
+    /// Circular sector, facing and half arc in radians
+    Cone {
+        origin: (f32, f32),
+        range: f32,
+        facing: f32,
+        half_arc: f32,
+    },
+    /// Rectangle rotated around its center, facing in radians
+    Rectangle {
+        center: (f32, f32),
+        half_size: (f32, f32),
+        facing: f32,
+    },
+}
+
+/// Non-radial area for influence, like Lurker spines, Disruptor shots or Liberator zones.
+/// Angles use the same convention as unit facing: radians, 0 pointing towards positive x and increasing counterclockwise.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct InfluenceShape {
+    shape: Shape,
+}
+
+#[pymethods]
+impl InfluenceShape {
+    /// Straight line from start to end with flat ends
+    #[staticmethod]
+    pub fn line(start: (f32, f32), end: (f32, f32), width: f32) -> Self {
+        let center = ((start.0 + end.0) * 0.5, (start.1 + end.1) * 0.5);
+        let length = euclidean_distance(start, end);
+        let facing = (end.1 - start.1).atan2(end.0 - start.0);
+        InfluenceShape::rectangle(center, (length, width), facing)
+    }
+
+    /// Line from start to end with rounded ends, a circle when start and end are the same
+    #[staticmethod]
+    pub fn capsule(start: (f32, f32), end: (f32, f32), radius: f32) -> Self {
+        InfluenceShape { shape: Shape::Capsule { start,
+                                                 end,
+                                                 radius } }
+    }
+
+    /// Circular sector with the total arc width centered on facing
+    #[staticmethod]
+    pub fn cone(origin: (f32, f32), range: f32, facing: f32, arc: f32) -> Self {
+        InfluenceShape { shape: Shape::Cone { origin,
+                                              range,
+                                              facing,
+                                              half_arc: arc * 0.5 } }
+    }
+
+    /// Rectangle of size (length, width) where length is along facing
+    #[staticmethod]
+    pub fn rectangle(center: (f32, f32), size: (f32, f32), facing: f32) -> Self {
+        InfluenceShape { shape: Shape::Rectangle { center,
+                                                   half_size: (size.0 * 0.5, size.1 * 0.5),
+                                                   facing } }
+    }
+
+    /// Distance from the point to the shape, 0 inside the shape
+    pub fn distance(&self, point: (f32, f32)) -> f32 {
+        match self.shape {
+            Shape::Capsule { start,
+                             end,
+                             radius, } => f32::max(0.0, segment_distance(point, start, end) - radius),
+            Shape::Cone { origin,
+                          range,
+                          facing,
+                          half_arc, } => {
+                let d = euclidean_distance(origin, point);
+                if d == 0.0 {
+                    return 0.0;
+                }
+
+                let angle = (point.1 - origin.1).atan2(point.0 - origin.0);
+                let inside_arc = angle_difference(angle, facing) <= half_arc;
+                if inside_arc && d <= range {
+                    return 0.0;
+                }
+
+                let edge1 = polar(origin, facing - half_arc, range);
+                let edge2 = polar(origin, facing + half_arc, range);
+                let mut best = f32::min(segment_distance(point, origin, edge1), segment_distance(point, origin, edge2));
+                if inside_arc {
+                    best = f32::min(best, d - range);
+                }
+                best
+            }
+            Shape::Rectangle { center,
+                               half_size,
+                               facing, } => {
+                let (sin, cos) = facing.sin_cos();
+                let dx = point.0 - center.0;
+                let dy = point.1 - center.1;
+                let local_x = dx * cos + dy * sin;
+                let local_y = -dx * sin + dy * cos;
+                let out_x = f32::max(0.0, local_x.abs() - half_size.0);
+                let out_y = f32::max(0.0, local_y.abs() - half_size.1);
+                (out_x * out_x + out_y * out_y).sqrt()
+            }
+        }
+    }
+}
+
+impl InfluenceShape {
+    /// Bounding box of the shape as (min_x, min_y, max_x, max_y)
+    fn bounds(&self) -> (f32, f32, f32, f32) {
+        match self.shape {
+            Shape::Capsule { start,
+                             end,
+                             radius, } => (f32::min(start.0, end.0) - radius,
+                                           f32::min(start.1, end.1) - radius,
+                                           f32::max(start.0, end.0) + radius,
+                                           f32::max(start.1, end.1) + radius),
+            Shape::Cone { origin,
+                          range,
+                          .. } => (origin.0 - range, origin.1 - range, origin.0 + range, origin.1 + range),
+            Shape::Rectangle { center,
+                               half_size,
+                               .. } => {
+                let r = (half_size.0 * half_size.0 + half_size.1 * half_size.1).sqrt();
+                (center.0 - r, center.1 - r, center.0 + r, center.1 + r)
+            }
+        }
+    }
+
+    /// Calls `visit` with the influence value for every cell inside the shape or the fade margin around it.
+    fn visit_cells<F>(&self, width: usize, height: usize, influence: f32, fade: f32, mut visit: F)
+        where F: FnMut(usize, usize, f32)
+    {
+        let fade = f32::max(0.0, fade);
+        let (min_x, min_y, max_x, max_y) = self.bounds();
+        let x_start = f32::max(0.0, (min_x - fade).floor()) as usize;
+        let y_start = f32::max(0.0, (min_y - fade).floor()) as usize;
+        let x_end = usize::min(width, f32::max(0.0, (max_x + fade).ceil() + 1.0) as usize);
+        let y_end = usize::min(height, f32::max(0.0, (max_y + fade).ceil() + 1.0) as usize);
+
+        for x in x_start..x_end {
+            for y in y_start..y_end {
+                let d = self.distance((x as f32, y as f32));
+                if d == 0.0 {
+                    visit(x, y, influence);
+                } else if d < fade {
+                    visit(x, y, influence * (1.0 - d / fade));
+                }
+            }
+        }
+    }
+
+    /// Adds influence to pathable cells inside the shape.
+    /// With fade above zero the influence fades out linearly over that distance from the edge of the shape.
+    pub fn apply(&self, map: &mut [Vec<usize>], influence: f32, fade: f32) {
+        let width = map.len();
+        let height = map[0].len();
+
+        self.visit_cells(width, height, influence, fade, |x, y, value| {
+                if map[x][y] > 0 {
+                    map[x][y] += value as usize;
+                }
+            });
+    }
+
+    /// Same as `apply`, but only cells inside the rectangle are changed.
+    pub(crate) fn apply_within(&self, map: &mut [Vec<usize>], influence: f32, fade: f32, rect: &Rectangle) {
+        let width = map.len();
+        let height = map[0].len();
+
+        self.visit_cells(width, height, influence, fade, |x, y, value| {
+                if rect.contains((x, y)) && map[x][y] > 0 {
+                    map[x][y] += value as usize;
+                }
+            });
+    }
+
+    /// Removes influence previously added with `apply`, pathable cells never go below 1.
+    pub fn remove(&self, map: &mut [Vec<usize>], influence: f32, fade: f32) {
+        let width = map.len();
+        let height = map[0].len();
+
+        self.visit_cells(width, height, influence, fade, |x, y, value| {
+                if map[x][y] > 0 {
+                    map[x][y] = usize::max(1, map[x][y].saturating_sub(value as usize));
+                }
+            });
+    }
+}
+
+#[pymethods]
+impl PathFind {
+    /// Adds influence inside the shape, fading out over fade distance outside of it.
+    pub fn add_influence_shape(&mut self, shape: &InfluenceShape, influence: f32, fade: Option<f32>) {
+        shape.apply(&mut self.map, influence, fade.unwrap_or(0.0));
+    }
+}
+
+#[inline]
+fn polar(origin: (f32, f32), angle: f32, distance: f32) -> (f32, f32) {
+    let (sin, cos) = angle.sin_cos();
+    (origin.0 + cos * distance, origin.1 + sin * distance)
+}
+
+/// Absolute difference between two angles in range 0..PI
+#[inline]
+fn angle_difference(angle1: f32, angle2: f32) -> f32 {
+    let d = (angle1 - angle2).rem_euclid(std::f32::consts::TAU);
+    f32::min(d, std::f32::consts::TAU - d)
+}
+
+fn segment_distance(point: (f32, f32), start: (f32, f32), end: (f32, f32)) -> f32 {
+    let dx = end.0 - start.0;
+    let dy = end.1 - start.1;
+    let length2 = dx * dx + dy * dy;
+    if length2 == 0.0 {
+        return euclidean_distance(point, start);
+    }
+
+    let t = ((point.0 - start.0) * dx + (point.1 - start.1) * dy) / length2;
+    let t = t.clamp(0.0, 1.0);
+    euclidean_distance(point, (start.0 + t * dx, start.1 + t * dy))
+}
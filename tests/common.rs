@@ -48,3 +48,25 @@ pub fn get_choke_map() -> Map {
     let map = Map::new(grid, grid2, grid_height, 2, 2, 38, 38, reaper_overrides);
     map
 }
+
+/// 40x40 map with open ground and two unwalkable plateaus that are high enough for overlords.
+pub fn get_plateau_map() -> Map {
+    let size = 40;
+    let mut grid = vec![vec![0; size]; size];
+    let mut grid_height = vec![vec![16; size]; size];
+
+    for x in 2..38 {
+        for y in 2..38 {
+            let plateau1 = (24..30).contains(&x) && (24..30).contains(&y);
+            let plateau2 = (6..10).contains(&x) && (28..32).contains(&y);
+            if plateau1 || plateau2 {
+                grid_height[x][y] = 48;
+            } else {
+                grid[x][y] = 1;
+            }
+        }
+    }
+
+    let reaper_overrides: Vec<Vec<usize>> = Vec::new();
+    Map::new(grid.clone(), grid, grid_height, 2, 2, 38, 38, reaper_overrides)
+}
@@ -1,5 +1,8 @@
-use common::{get_choke_map, read_vec_from_file};
-use sc2pathlib::mapping::{map::Map, vision::VisionUnit};
+use std::collections::HashSet;
+
+use common::{get_choke_map, get_plateau_map, read_vec_from_file};
+use sc2pathlib::mapping::{map::Map, threats::ThreatUnit, vision::VisionUnit};
+use sc2pathlib::path_find::{euclidean_distance, shapes::InfluenceShape};
 mod common;
 
 #[test]
@@ -31,7 +34,7 @@ fn test_find_map_chokes() {
 #[test]
 fn test_ray_vision() {
     let mut map = get_choke_map();
-    let vision_unit = VisionUnit::new(false, false, (18f32, 8f32), 10f32);
+    let vision_unit = VisionUnit::new(false, false, (18f32, 8f32), 10f32, None);
     map.add_vision_unit(vision_unit);
     map.calculate_vision_map();
 
@@ -44,7 +47,7 @@ fn test_ray_vision() {
 #[test]
 fn test_flying_vision() {
     let mut map = get_choke_map();
-    let vision_unit = VisionUnit::new(false, true, (19f32, 8f32), 10f32);
+    let vision_unit = VisionUnit::new(false, true, (19f32, 8f32), 10f32, None);
     map.add_vision_unit(vision_unit);
     map.calculate_vision_map();
 
@@ -52,3 +55,576 @@ fn test_flying_vision() {
     assert_eq!(map.vision_status((27f32, 8f32)), 1);
     assert_eq!(map.vision_status((31f32, 8f32)), 0);
 }
+
+#[test]
+fn test_overlord_spot_details() {
+    let mut map = get_plateau_map();
+    map.calculate_overlord_spots(vec![(27f32, 35f32), (5f32, 5f32), (-10f32, 500f32)], None, None);
+
+    let spot = map.overlord_spots.iter().find(|s| s.size == 36).unwrap();
+    assert_eq!(map.overlord_spots.len(), 2);
+    assert_eq!(spot.height, 48);
+    let ground_distance = spot.ground_distance.unwrap();
+    assert!(ground_distance > 3.0 && ground_distance < 4.0);
+    assert_eq!(spot.visible_expansions, vec![0]);
+    assert!(spot.exposure > 0.0);
+}
+
+#[test]
+fn test_best_overlord_spots() {
+    let map = get_plateau_map();
+    let base = (27f32, 12f32);
+    let best = map.best_overlord_spots(base, vec![], 2);
+    assert_eq!(best.len(), 2);
+    assert_eq!(best[0].size, 36);
+
+    let occupied = vec![best[0].position];
+    let best = map.best_overlord_spots(base, occupied, 2);
+    assert_eq!(best.len(), 1);
+    assert_eq!(best[0].size, 16);
+}
+
+#[test]
+fn test_vision_memory() {
+    let mut map = get_choke_map();
+    map.set_vision_unit(1, VisionUnit::new(false, true, (10f32, 10f32), 4f32, None));
+    map.update_vision_memory(100);
+    map.move_vision_unit(1, (30f32, 30f32));
+    map.update_vision_memory(300);
+
+    assert_eq!(map.vision_staleness((10f32, 10f32), 400), 300);
+    assert_eq!(map.vision_staleness((30f32, 30f32), 400), 100);
+    assert_eq!(map.vision_staleness((15f32, 20f32), 400), 400);
+
+    let stale = map.cells_not_seen_for(400, 200);
+    assert!(stale.contains(&(10, 10)));
+    assert!(!stale.contains(&(30, 30)));
+
+    let oldest = map.oldest_seen_cells((10f32, 10f32), 6f32, 3);
+    assert_eq!(oldest.len(), 3);
+    assert!(oldest.iter().all(|(_, last_seen)| *last_seen == 0));
+
+    map.normalize_influence(10);
+    map.add_influence_by_staleness(0, 400, 100f32, 400);
+    assert_eq!(map.ground_pathing.map[15][20], 110);
+    assert_eq!(map.ground_pathing.map[10][10], 85);
+}
+
+#[test]
+fn test_watchtower_vision() {
+    let mut map = get_choke_map();
+    map.set_watchtowers(vec![((10f32, 10f32), 5f32)]);
+    assert_eq!(map.vision_status((10f32, 13f32)), 0);
+
+    assert!(map.set_watchtower_occupied((11f32, 10f32), true));
+    assert!(!map.set_watchtower_occupied((30f32, 30f32), true));
+    assert_eq!(map.vision_status((10f32, 13f32)), 1);
+
+    map.clear_vision();
+    assert_eq!(map.vision_status((10f32, 13f32)), 1);
+
+    map.set_watchtower_occupied((10f32, 10f32), false);
+    assert_eq!(map.vision_status((10f32, 13f32)), 0);
+}
+
+#[test]
+fn test_los_blockers_size() {
+    let mut map = get_choke_map();
+    let width = map.points.len();
+    let height = map.points[0].len();
+
+    assert!(map.set_los_blockers(vec![vec![0; height]; width]).is_ok());
+    assert!(map.set_los_blockers(vec![vec![0; height]; width + 1]).is_err());
+    assert!(map.set_los_blockers(vec![vec![0; height - 1]; width]).is_err());
+}
+
+#[test]
+fn test_find_path_stealth() {
+    let mut map = get_plateau_map();
+    map.add_enemy_vision_unit(VisionUnit::new(true, true, (20f32, 12f32), 6f32, None));
+    map.calculate_enemy_vision_map();
+    assert_eq!(map.enemy_vision_status((20f32, 12f32)), 2);
+
+    let (path, _) = map.find_path_basic(0, (5f32, 12f32), (35f32, 12f32), Some(1));
+    assert!(path.iter().any(|p| map.enemy_vision_status((p.0 as f32, p.1 as f32)) > 0));
+
+    let (path, _) = map.find_path_stealth(0, (5f32, 12f32), (35f32, 12f32), 50, 100, Some(1));
+    assert_eq!(path.last(), Some(&(35, 12)));
+    assert!(path.iter().all(|p| map.enemy_vision_status((p.0 as f32, p.1 as f32)) == 0));
+}
+
+#[test]
+fn test_find_path_stealth_detection_only() {
+    let mut map = get_plateau_map();
+    // Detection reaches much further than sight, like a spore crawler or a raven
+    map.add_enemy_vision_unit(VisionUnit::new(true, true, (20f32, 12f32), 0f32, Some(6f32)));
+    map.calculate_enemy_vision_map();
+    assert_eq!(map.enemy_vision_status((20f32, 15f32)), 3);
+
+    let (path, _) = map.find_path_basic(0, (5f32, 12f32), (35f32, 12f32), Some(1));
+    assert!(path.iter().any(|p| map.enemy_vision_status((p.0 as f32, p.1 as f32)) == 3));
+
+    let (path, _) = map.find_path_stealth(0, (5f32, 12f32), (35f32, 12f32), 0, 100, Some(1));
+    assert_eq!(path.last(), Some(&(35, 12)));
+    assert!(path.iter().all(|p| map.enemy_vision_status((p.0 as f32, p.1 as f32)) == 0));
+}
+
+#[test]
+fn test_add_threats() {
+    let mut map = get_plateau_map();
+    let air_before = map.air_pathing.map[20][15];
+    let tank = ThreatUnit::new((15f32, 15f32), 5f32, 2f32, 20f32, true, false, 1f32);
+    map.add_threats(vec![tank], Some(2f32));
+
+    // Inside minimum range
+    assert_eq!(map.ground_pathing.map[15][15], 1);
+    assert_eq!(map.ground_pathing.map[17][15], 1);
+    // Within weapon range plus radius
+    assert_eq!(map.ground_pathing.map[20][15], 21);
+    // Fading
+    assert_eq!(map.ground_pathing.map[22][15], 11);
+    assert_eq!(map.ground_pathing.map[24][15], 1);
+    // Ground weapon doesn't affect air
+    assert_eq!(map.air_pathing.map[20][15], air_before);
+
+    let viking = ThreatUnit::new((15f32, 15f32), 9f32, 0f32, 14f32, false, true, 0.75f32);
+    map.add_threats(vec![viking], None);
+    assert_eq!(map.air_pathing.map[15][15], air_before + 14);
+    assert_eq!(map.ground_pathing.map[15][15], 1);
+}
+
+#[test]
+fn test_hazards_expire() {
+    let mut map = get_plateau_map();
+    let storm = InfluenceShape::capsule((15f32, 15f32), (15f32, 15f32), 2f32);
+    map.add_hazard(storm, 50f32, 100, Some(1), None);
+    map.add_timed_block((20f32, 10f32), (2, 2), 50);
+    map.add_timed_block((21f32, 10f32), (2, 2), 80);
+
+    assert_eq!(map.ground_pathing.map[15][15], 51);
+    assert_eq!(map.air_pathing.map[15][15], 1);
+    assert_eq!(map.ground_pathing.map[19][9], 0);
+
+    // Hazards survive resets and static influence survives expiry
+    map.reset();
+    map.add_influence_fading(1, vec![(15f32, 15f32)], 10f32, 1f32, 2f32);
+    assert_eq!(map.ground_pathing.map[15][15], 61);
+    assert_eq!(map.ground_pathing.map[19][9], 0);
+
+    map.advance(50);
+    assert_eq!(map.ground_pathing.map[19][9], 1);
+    assert_eq!(map.ground_pathing.map[20][9], 0);
+    assert_eq!(map.timed_blocks.len(), 1);
+
+    map.advance(100);
+    assert_eq!(map.ground_pathing.map[15][15], 11);
+    assert_eq!(map.ground_pathing.map[20][9], 1);
+    assert!(map.hazards.is_empty());
+
+    // Already expired hazards are ignored
+    map.add_hazard(InfluenceShape::capsule((15f32, 15f32), (15f32, 15f32), 2f32), 50f32, 90, None, None);
+    assert_eq!(map.ground_pathing.map[15][15], 11);
+}
+
+#[test]
+fn test_timed_block_expiry_keeps_other_blocks() {
+    let mut map = get_plateau_map();
+    map.normalize_influence(10);
+    map.add_hazard(InfluenceShape::capsule((20f32, 20f32), (20f32, 20f32), 3f32), 50f32, 200, Some(1), None);
+    map.add_decay_layer("army".to_string(), 1000f32, 1).unwrap();
+    map.observe_decay_layer("army", vec![(20f32, 20f32)], 40f32, 6f32, 1f32).unwrap();
+    let air_before = map.air_pathing.map[18][18];
+
+    let colossus_before = map.colossus_pathing.map[18][18];
+
+    map.add_timed_block((20f32, 20f32), (4, 4), 50);
+    // Colossi walk over force fields
+    assert_eq!(map.colossus_pathing.map[18][18], colossus_before);
+    // Building placed on top of the force field
+    map.create_block((21f32, 20f32), (2, 2));
+    assert_eq!(map.ground_pathing.map[18][18], 0);
+
+    map.advance(50);
+    assert!(map.timed_blocks.is_empty());
+    // Cells under the building stay blocked
+    assert_eq!(map.ground_pathing.map[20][19], 0);
+    assert_eq!(map.ground_pathing.map[21][20], 0);
+    assert_eq!(map.colossus_pathing.map[21][20], 0);
+    // Opened cells get normal influence, hazard and decay back like the mirrored cell outside the block
+    assert!(map.ground_pathing.map[18][18] > 60);
+    assert_eq!(map.ground_pathing.map[18][18], map.ground_pathing.map[22][22]);
+    assert_eq!(map.air_pathing.map[18][18], air_before);
+    assert_eq!(map.colossus_pathing.map[18][18], colossus_before);
+
+    // Removing a building doesn't open a force field on top of it
+    map.create_block((10f32, 10f32), (2, 2));
+    map.add_timed_block((10f32, 10f32), (4, 4), 100);
+    map.remove_blocks(vec![(10f32, 10f32)], (2, 2));
+    assert_eq!(map.ground_pathing.map[10][10], 0);
+    assert_eq!(map.reaper_pathing.map[10][10], 0);
+}
+
+#[test]
+fn test_decay_layer() {
+    let mut map = get_plateau_map();
+    map.add_decay_layer("army".to_string(), 100f32, 1).unwrap();
+    assert!(map.observe_decay_layer("army", vec![(15f32, 15f32)], 40f32, 4f32, 1f32).unwrap());
+    assert_eq!(map.ground_pathing.map[15][15], 41);
+    assert_eq!(map.air_pathing.map[15][15], 1);
+
+    // Remembered through resets while decaying
+    map.advance(100);
+    map.reset();
+    assert_eq!(map.ground_pathing.map[15][15], 21);
+    assert_eq!(map.decay_influence("army", (15f32, 15f32)), 20f32);
+
+    let (lowest, _) = map.lowest_influence(0, (15f32, 15f32), 2);
+    assert_ne!(lowest, (15, 15));
+
+    map.advance(200);
+    assert!(map.observe_decay_layer("army", vec![(15f32, 15f32)], 40f32, 4f32, 0.5f32).unwrap());
+    assert_eq!(map.ground_pathing.map[15][15], 26);
+
+    assert!(map.remove_decay_layer("army"));
+    assert!(!map.observe_decay_layer("army", vec![(15f32, 15f32)], 40f32, 4f32, 1f32).unwrap());
+    assert_eq!(map.ground_pathing.map[15][15], 1);
+
+    // Invalid half life or distance is an error instead of broken values
+    assert!(map.add_decay_layer("army".to_string(), 0f32, 1).is_err());
+    assert!(map.add_decay_layer("army".to_string(), -10f32, 1).is_err());
+    map.add_decay_layer("army".to_string(), 100f32, 1).unwrap();
+    assert!(map.observe_decay_layer("army", vec![(15f32, 15f32)], 40f32, 0f32, 1f32).is_err());
+
+    // Huge observations are capped instead of overflowing the pathing values
+    map.observe_decay_layer("army", vec![(15f32, 15f32)], f32::MAX, 4f32, 1f32).unwrap();
+    map.observe_decay_layer("army", vec![(15f32, 15f32)], f32::MAX, 4f32, 1f32).unwrap();
+    assert_eq!(map.ground_pathing.map[15][15], 65536);
+}
+
+#[test]
+fn test_distance_fields() {
+    let mut map = get_plateau_map();
+    let sources = vec![(5f32, 5f32), (34f32, 34f32)];
+    map.build_distance_field("bases".to_string(), sources.clone(), 0);
+
+    assert_eq!(map.distance_from("bases", (5f32, 5f32)), Some(0f32));
+    for point in [(20f32, 30f32), (32f32, 20f32), (8f32, 34f32)] {
+        let expected = sources.iter()
+                              .map(|s| map.find_path(0, point, *s, false, false, None, None, None).1)
+                              .fold(f32::MAX, f32::min);
+        let distance = map.distance_from("bases", point).unwrap();
+        assert!((distance - expected).abs() < 0.01);
+
+        let path = map.path_from("bases", point).unwrap();
+        assert_eq!(path[0], (point.0 as usize, point.1 as usize));
+        let end = *path.last().unwrap();
+        assert!(end == (5, 5) || end == (34, 34));
+        assert!(path.iter().all(|p| map.ground_pathing.map[p.0][p.1] > 0));
+    }
+
+    // Plateau cells can't be reached by ground
+    assert_eq!(map.distance_from("bases", (26f32, 26f32)), None);
+    assert_eq!(map.distance_from("missing", (20f32, 20f32)), None);
+    assert!(map.remove_distance_field("bases"));
+    assert_eq!(map.path_from("bases", (20f32, 20f32)), None);
+}
+
+#[test]
+fn test_zone_distances() {
+    let mut map = get_plateau_map();
+    map.calculate_zones(vec![(5f32, 5f32), (34f32, 34f32)]);
+
+    let ground = map.find_path(0, (5f32, 5f32), (34f32, 34f32), false, false, None, None, None).1;
+    assert!((map.zone_distance(1, 2, 0).unwrap() - ground).abs() < 0.01);
+    assert_eq!(map.zone_distance(2, 1, 0), map.zone_distance(1, 2, 0));
+    assert_eq!(map.zone_distance(1, 1, 0), Some(0f32));
+    assert!((map.zone_distance(1, 2, 3).unwrap() - 29f32 * std::f32::consts::SQRT_2).abs() < 0.01);
+    assert_eq!(map.zone_distance(1, 3, 0), None);
+
+    // Wall between the zones
+    let wall: Vec<(f32, f32)> = (2..38).map(|x| (x as f32, 20f32)).collect();
+    map.create_blocks(wall.clone(), (1, 1));
+    assert_eq!(map.zone_distance(1, 2, 0), None);
+    assert!(map.zone_distance(1, 2, 3).is_some());
+
+    map.remove_blocks(wall, (1, 1));
+    assert!((map.zone_distance(1, 2, 0).unwrap() - ground).abs() < 0.01);
+
+    // Wall with a gap makes the path longer, blocks away from the path change nothing
+    let partial: Vec<(f32, f32)> = (2..30).map(|x| (x as f32, 20f32)).collect();
+    map.create_blocks(partial, (1, 1));
+    map.create_block((30f32, 5f32), (2, 2));
+    let detour = map.find_path(0, (5f32, 5f32), (34f32, 34f32), false, false, None, None, None).1;
+    assert!(detour > ground);
+    assert!((map.zone_distance(1, 2, 0).unwrap() - detour).abs() < 0.01);
+    assert!((map.zone_distance(1, 2, 1).unwrap() - detour).abs() < 0.01);
+
+    map.reset();
+    assert!((map.zone_distance(1, 2, 0).unwrap() - ground).abs() < 0.01);
+}
+
+#[test]
+fn test_find_attack_positions() {
+    let size = 40;
+    let mut grid = vec![vec![0; size]; size];
+    let mut grid_height = vec![vec![16; size]; size];
+    for x in 2..38 {
+        for y in 2..38 {
+            grid[x][y] = 1;
+            if x >= 20 {
+                grid_height[x][y] = 32;
+            }
+        }
+    }
+    let map = Map::new(grid.clone(), grid, grid_height, 2, 2, 38, 38, Vec::new());
+
+    let positions = map.find_attack_positions(0, (15f32, 20f32), 6f32, 0.5f32, 5, None);
+    assert_eq!(positions.len(), 5);
+    assert!(positions.iter().all(|(p, _)| p.0 >= 20));
+
+    let positions = map.find_attack_positions(0, (15f32, 20f32), 6f32, 0.5f32, 200, Some((5f32, 20f32)));
+    let (best, _) = positions[0];
+    assert_eq!(best, (9, 20));
+    assert!(positions.windows(2).all(|w| w[0].1 <= w[1].1));
+    assert!(positions.iter().all(|(p, _)| euclidean_distance((15f32, 20f32), (p.0 as f32, p.1 as f32)) <= 6.5f32));
+}
+
+#[test]
+fn test_islands() {
+    let size = 40;
+    let mut grid = vec![vec![0; size]; size];
+    let grid_height = vec![vec![16; size]; size];
+    for (x, column) in grid.iter_mut().enumerate().take(38).skip(2) {
+        for (y, cell) in column.iter_mut().enumerate().take(38).skip(2) {
+            let mainland = x < 22;
+            let island = x >= 28 && (6..18).contains(&y);
+            let pocket = (28..30).contains(&x) && (30..32).contains(&y);
+            if mainland || island || pocket {
+                *cell = 1;
+            }
+        }
+    }
+    // Mineral field on the island
+    grid[33][10] = 0;
+    let map = Map::new(grid.clone(), grid, grid_height, 2, 2, 38, 38, Vec::new());
+
+    let resources = vec![(33f32, 10f32), (10f32, 10f32)];
+    let islands = map.islands(vec![(8f32, 20f32)], Some(resources), None);
+    assert_eq!(islands.len(), 1);
+
+    let island = &islands[0];
+    assert_eq!(island.area, 10 * 12 - 1);
+    assert!((island.centroid.0 - 32.5).abs() < 0.1);
+    assert_eq!(island.resources, vec![(33f32, 10f32)]);
+    assert_eq!(island.drop_points.len(), 3);
+    let (closest, distance) = island.drop_points[0];
+    assert_eq!(closest.0, 28);
+    assert!((distance - 7f32).abs() < 0.01);
+    assert!(island.drop_points.windows(2).all(|w| w[0].1 <= w[1].1));
+
+    // Small pockets are included when asked for
+    let islands = map.islands(vec![(8f32, 20f32)], None, Some(1));
+    assert_eq!(islands.len(), 2);
+    assert_eq!(islands[1].area, 4);
+    assert!(map.islands(vec![(8f32, 20f32), (33f32, 15f32)], None, None).is_empty());
+}
+
+#[test]
+fn test_find_drop_spots() {
+    let size = 40;
+    let mut grid = vec![vec![0; size]; size];
+    let mut grid_height = vec![vec![16; size]; size];
+    for x in 2..38 {
+        for y in 2..38 {
+            grid[x][y] = 1;
+            if x >= 24 && y >= 24 {
+                // Heights vary a little within a level
+                grid_height[x][y] = 32 + (x * 7 + y * 13) % 11;
+            }
+        }
+    }
+    let mut map = Map::new(grid.clone(), grid, grid_height, 2, 2, 38, 38, Vec::new());
+
+    let spots = map.find_drop_spots((30f32, 30f32), (5f32, 30f32), Vec::new(), 10, Some(6f32));
+    assert_eq!(spots.len(), 10);
+    assert!(spots.iter().all(|s| s.position.0 >= 24 && s.position.1 >= 24));
+    assert!(spots.windows(2).all(|w| w[0].score <= w[1].score));
+    let best = &spots[0];
+    assert_eq!(best.air_path[0], (5, 30));
+    assert_eq!(*best.air_path.last().unwrap(), best.position);
+    assert_eq!(best.exposure, 0f32);
+
+    // Mineral fields aren't pathable, the spots are still on their level
+    map.create_block((33f32, 33f32), (2, 2));
+    let spots = map.find_drop_spots((33f32, 33f32), (5f32, 30f32), Vec::new(), 5, Some(6f32));
+    assert_eq!(spots.len(), 5);
+    assert!(spots.iter().all(|s| s.position.0 >= 24 && s.position.1 >= 24));
+    map.reset();
+
+    // A defense next to the target pushes the spot out of its range
+    let spots = map.find_drop_spots((30f32, 30f32), (5f32, 30f32), vec![(30f32, 36f32)], 1, Some(6f32));
+    assert!(spots[0].defense_distance.unwrap() >= 10f32);
+
+    // Anti-air on the way is avoided
+    map.add_influence_fading(2, vec![(22f32, 30f32)], 50f32, 0f32, 5f32);
+    let spots = map.find_drop_spots((30f32, 30f32), (5f32, 30f32), Vec::new(), 1, Some(6f32));
+    assert!(spots[0].air_path.iter().all(|p| euclidean_distance((22f32, 30f32), (p.0 as f32, p.1 as f32)) >= 4f32));
+}
+
+#[test]
+fn test_find_transport_path() {
+    let size = 40;
+    let mut grid = vec![vec![0; size]; size];
+    let grid_height = vec![vec![16; size]; size];
+    for (x, column) in grid.iter_mut().enumerate().take(38).skip(2) {
+        for (y, cell) in column.iter_mut().enumerate().take(38).skip(2) {
+            // Long wall with a gap at the far end
+            if x != 20 || y >= 34 {
+                *cell = 1;
+            }
+        }
+    }
+    let map = Map::new(grid.clone(), grid, grid_height, 2, 2, 38, 38, Vec::new());
+    let walk = map.find_path(0, (10f32, 5f32), (30f32, 5f32), false, false, None, None, None).1;
+
+    let (legs, total) = map.find_transport_path((10f32, 5f32), (30f32, 5f32), None, None, None).unwrap();
+    assert!(total < walk);
+    assert!(legs.iter().any(|leg| leg.mode == 1));
+    assert_eq!(legs[0].path[0], (10, 5));
+    assert_eq!(*legs.last().unwrap().path.last().unwrap(), (30, 5));
+    assert!(legs.windows(2).all(|w| *w[0].path.last().unwrap() == w[1].path[0]));
+    assert!((legs.iter().map(|leg| leg.cost).sum::<f32>() - total).abs() < 0.01);
+
+    // Walking is cheaper when getting in and out takes long enough
+    let (legs, total) = map.find_transport_path((10f32, 5f32), (30f32, 5f32), None, Some(40f32), Some(40f32)).unwrap();
+    assert_eq!(legs.len(), 1);
+    assert_eq!(legs[0].mode, 0);
+    assert!((total - walk).abs() < 0.01);
+
+    // Already at the goal
+    let (legs, total) = map.find_transport_path((10f32, 5f32), (10f32, 5f32), None, None, None).unwrap();
+    assert_eq!(legs.len(), 1);
+    assert_eq!(legs[0].mode, 0);
+    assert_eq!(legs[0].path, vec![(10, 5)]);
+    assert_eq!(total, 0f32);
+
+    assert!(map.find_transport_path((10f32, 5f32), (30f32, 5f32), Some(0f32), None, None).is_none());
+    assert!(map.find_transport_path((10f32, 5f32), (30f32, 5f32), Some(-1f32), None, None).is_none());
+}
+
+#[test]
+fn test_concave_positions() {
+    let map = get_plateau_map();
+    let units: Vec<((f32, f32), f32)> = (0..5).map(|i| ((10f32, 10f32 + i as f32), 0.5f32)).collect();
+    let positions: Vec<(f32, f32)> =
+        map.concave_positions(units.clone(), (20f32, 12f32), 4f32).into_iter().map(|p| p.unwrap()).collect();
+
+    for (i, p) in positions.iter().enumerate() {
+        assert!((euclidean_distance(*p, (20f32, 12f32)) - 4.5f32).abs() < 0.01);
+        assert!(p.0 < 20f32);
+        for other in positions.iter().skip(i + 1) {
+            assert!(euclidean_distance(*p, *other) >= 0.99f32);
+        }
+    }
+
+    // No other assignment of the same slots has less travel
+    let total =
+        |slots: &[(f32, f32)]| -> f32 { units.iter().zip(slots).map(|(u, s)| euclidean_distance(u.0, *s)).sum() };
+    let best = total(&positions);
+    for i in 0..positions.len() {
+        for j in (i + 1)..positions.len() {
+            let mut swapped = positions.clone();
+            swapped.swap(i, j);
+            assert!(total(&swapped) >= best - 0.001);
+        }
+    }
+
+    // Slots avoid the plateau next to the target
+    let units: Vec<((f32, f32), f32)> = (0..12).map(|i| ((20f32 + i as f32, 12f32), 0.5f32)).collect();
+    let positions = map.concave_positions(units, (26f32, 21f32), 3f32);
+    assert!(positions.iter().all(|p| p.is_some()));
+    for p in positions.iter().flatten() {
+        let cell = (p.0.round() as usize, p.1.round() as usize);
+        assert_eq!(map.ground_pathing.map[cell.0][cell.1], 1);
+    }
+
+    // Without range the first slot is the target itself
+    let units: Vec<((f32, f32), f32)> = (0..3).map(|i| ((10f32, 10f32 + i as f32), 0f32)).collect();
+    let positions = map.concave_positions(units, (20f32, 12f32), 0f32);
+    assert!(positions.contains(&Some((20f32, 12f32))));
+    assert!(positions.iter().flatten().all(|p| !p.0.is_nan() && !p.1.is_nan()));
+
+    // Many units fill rows behind the arc instead of surrounding the target
+    let units: Vec<((f32, f32), f32)> = (0..30).map(|i| ((4f32, 5f32 + i as f32), 0.5f32)).collect();
+    let target = (20f32, 19.5f32);
+    let positions = map.concave_positions(units, target, 6f32);
+    assert!(positions.iter().all(|p| p.is_some()));
+    assert!(positions.iter().flatten().all(|p| p.0 <= target.0 + 0.01));
+
+    // Units around a plateau have their center on it
+    let units = vec![((23f32, 27f32), 0.5f32), ((31f32, 27f32), 0.5f32)];
+    let positions = map.concave_positions(units, (27f32, 12f32), 3f32);
+    assert!(positions.iter().all(|p| p.is_some()));
+}
+
+#[test]
+fn test_formation_paths() {
+    let size = 40;
+    let mut grid = vec![vec![0; size]; size];
+    for (x, column) in grid.iter_mut().enumerate().take(38).skip(2) {
+        for (y, cell) in column.iter_mut().enumerate().take(38).skip(2) {
+            if x != 20 || (19..22).contains(&y) {
+                *cell = 1;
+            }
+        }
+    }
+    let grid_height = vec![vec![16; size]; size];
+    let map = Map::new(grid.clone(), grid, grid_height, 2, 2, 38, 38, Vec::new());
+
+    let path: Vec<(f32, f32)> = (5..36).map(|x| (x as f32, 20f32)).collect();
+    let units: Vec<(f32, f32)> = (0..5).map(|i| (3f32, 24f32 - 2f32 * i as f32)).collect();
+    let paths = map.formation_paths(0, units, path.clone(), 0, 2f32);
+    assert_eq!(paths.len(), 5);
+
+    for (unit, unit_path) in paths.iter().enumerate() {
+        assert_eq!(unit_path.len(), path.len());
+        // Spread out before and after the choke
+        let expected_y = 24f32 - 2f32 * unit as f32;
+        assert!((unit_path[0].1 - expected_y).abs() < 0.01);
+        assert!((unit_path[30].1 - expected_y).abs() < 0.01);
+        // Compressed through the choke
+        let choke = unit_path[15];
+        assert!((19f32..=21f32).contains(&choke.1.round()));
+        assert_eq!(map.ground_pathing.map[choke.0.round() as usize][choke.1.round() as usize], 1);
+    }
+
+    // Wedge and box rows follow behind the leader
+    let units: Vec<(f32, f32)> = (0..6).map(|i| (3f32, 15f32 + i as f32)).collect();
+    for formation in [1, 2] {
+        let paths = map.formation_paths(0, units.clone(), path.clone(), formation, 2f32);
+        let leader_x = paths.iter().map(|p| p[5].0).fold(0f32, f32::max);
+        assert_eq!(leader_x, 10f32);
+        assert!(paths.iter().any(|p| p[5].0 < 9f32));
+    }
+
+    // Single cell gap, units whose slots are blocked wait in separate free cells
+    let mut grid = vec![vec![0; size]; size];
+    for (x, column) in grid.iter_mut().enumerate().take(38).skip(2) {
+        for (y, cell) in column.iter_mut().enumerate().take(38).skip(2) {
+            if x != 20 || y == 20 {
+                *cell = 1;
+            }
+        }
+    }
+    let grid_height = vec![vec![16; size]; size];
+    let map = Map::new(grid.clone(), grid, grid_height, 2, 2, 38, 38, Vec::new());
+    let units: Vec<(f32, f32)> = (0..5).map(|i| (3f32, 16f32 + 2f32 * i as f32)).collect();
+    let paths = map.formation_paths(0, units, path.clone(), 0, 2f32);
+
+    for i in 0..path.len() {
+        let cells: HashSet<(usize, usize)> =
+            paths.iter().map(|p| (p[i].0.round() as usize, p[i].1.round() as usize)).collect();
+        assert_eq!(cells.len(), paths.len());
+        assert!(cells.iter().all(|c| map.ground_pathing.map[c.0][c.1] > 0));
+    }
+}
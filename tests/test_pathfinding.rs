@@ -1,8 +1,11 @@
 use common::get_pathfind;
 use sc2pathlib::helpers::point2_f32;
 use sc2pathlib::helpers::round_point2;
+use sc2pathlib::path_find::euclidean_distance;
 use sc2pathlib::path_find::octile_distance;
 use sc2pathlib::path_find::octile_distance_f32;
+use sc2pathlib::path_find::shapes::InfluenceShape;
+use sc2pathlib::path_find::PathFind;
 
 mod common;
 
@@ -126,3 +129,281 @@ fn test_find_low_inside_far2() {
     assert!(distance <= 8f32);
     assert_eq!(influence, 1);
 }
+
+#[test]
+fn test_influence_shapes() {
+    let mut path_find = PathFind::new_internal(vec![vec![1; 30]; 30]);
+
+    path_find.add_influence_shape(&InfluenceShape::line((5f32, 15f32), (15f32, 15f32), 1f32), 10f32, None);
+    assert_eq!(path_find.map[10][15], 11);
+    assert_eq!(path_find.map[15][15], 11);
+    assert_eq!(path_find.map[10][16], 1);
+    assert_eq!(path_find.map[17][15], 1);
+
+    let cone = InfluenceShape::cone((15f32, 25f32), 6f32, 0f32, std::f32::consts::FRAC_PI_2);
+    path_find.add_influence_shape(&cone, 10f32, None);
+    assert_eq!(path_find.map[20][25], 11);
+    assert_eq!(path_find.map[18][27], 11);
+    assert_eq!(path_find.map[20][29], 1);
+    assert_eq!(path_find.map[12][25], 1);
+
+    let capsule = InfluenceShape::capsule((5f32, 3f32), (5f32, 8f32), 2f32);
+    path_find.add_influence_shape(&capsule, 10f32, None);
+    assert_eq!(path_find.map[6][9], 11);
+    assert_eq!(path_find.map[7][9], 1);
+
+    let rectangle = InfluenceShape::rectangle((20f32, 5f32), (6f32, 2f32), std::f32::consts::FRAC_PI_2);
+    path_find.add_influence_shape(&rectangle, 10f32, Some(2f32));
+    assert_eq!(path_find.map[20][8], 11);
+    assert_eq!(path_find.map[22][5], 6);
+    assert_eq!(path_find.map[24][5], 1);
+}
+
+#[test]
+fn test_path_exposure() {
+    let mut path_find = PathFind::new_internal(vec![vec![1; 30]; 30]);
+    path_find.add_influence_spot((10, 5), 9);
+
+    let exposure = path_find.path_exposure(vec![(0f32, 0f32), (0f32, 5f32), (20f32, 5f32)], 2f32, Some(5)).unwrap();
+    assert_eq!(exposure.total, 4.5);
+    assert_eq!(exposure.peak, 9);
+    assert_eq!(exposure.time_above, 0.5);
+    assert_eq!(exposure.worst_segment, Some(1));
+
+    let exposure = path_find.path_exposure(vec![(0f32, 0f32), (20f32, 0f32)], 2f32, None).unwrap();
+    assert_eq!(exposure.total, 0f32);
+    assert_eq!(exposure.peak, 0);
+    assert_eq!(exposure.worst_segment, None);
+
+    assert!(path_find.path_exposure(vec![(0f32, 0f32), (20f32, 5f32)], 0f32, None).is_err());
+    assert!(path_find.path_exposure(vec![(0f32, 0f32), (20f32, 5f32)], f32::NAN, None).is_err());
+}
+
+#[test]
+fn test_find_path_constrained() {
+    let mut path_find = PathFind::new_internal(vec![vec![1; 30]; 30]);
+    for y in 0..27 {
+        path_find.add_influence_spot((15, y), 20);
+    }
+
+    let (path, distance) = path_find.find_path_constrained((5, 15), (25, 15), None, None).unwrap();
+    assert_eq!(distance, 20f32);
+    assert_eq!(path.len(), 21);
+
+    // Going through the danger costs 20 exposure
+    let (_, distance) = path_find.find_path_constrained((5, 15), (25, 15), None, Some(25f32)).unwrap();
+    assert_eq!(distance, 20f32);
+
+    let (path, distance) = path_find.find_path_constrained((5, 15), (25, 15), None, Some(10f32)).unwrap();
+    assert!(distance > 20f32);
+    assert!(path.iter().all(|p| p.0 != 15 || p.1 >= 27));
+
+    let (path, _) = path_find.find_path_constrained((5, 15), (25, 15), Some(10), None).unwrap();
+    assert!(path.iter().all(|p| path_find.map[p.0][p.1] <= 11));
+
+    for y in 27..30 {
+        path_find.add_influence_spot((15, y), 20);
+    }
+    assert!(path_find.find_path_constrained((5, 15), (25, 15), Some(10), None).is_none());
+    assert!(path_find.find_path_constrained((5, 15), (25, 15), None, Some(10f32)).is_none());
+}
+
+#[test]
+fn test_find_path_constrained_gradual_influence() {
+    let mut path_find = PathFind::new_internal(vec![vec![1; 150]; 150]);
+    for x in 0..150 {
+        for y in 0..150 {
+            path_find.add_influence_spot((x, y), (x * 7 + y * 13) % 23);
+        }
+    }
+
+    // Every route costs far more than the budget, the search has to give up in reasonable time
+    assert!(path_find.find_path_constrained((5, 5), (145, 145), None, Some(50f32)).is_none());
+
+    let (path, _) = path_find.find_path_constrained((5, 5), (145, 145), None, Some(5000f32)).unwrap();
+    assert_eq!(path.last(), Some(&(145, 145)));
+}
+
+#[test]
+fn test_find_flee_path() {
+    let mut path_find = PathFind::new_internal(vec![vec![1; 30]; 30]);
+
+    let (path, distance) = path_find.find_flee_path((15f32, 15f32), vec![(15f32, 10f32)], 8f32);
+    let end = *path.last().unwrap();
+    assert_eq!(path[0], (15, 15));
+    assert!(end.1 > 20);
+    assert!(distance <= 8f32);
+    assert!(path.windows(2).all(|s| octile_distance(s[0], s[1]) <= 14142));
+
+    // Going around a wall counts as walking, not as influence
+    let mut walled = PathFind::new_internal(vec![vec![0; 30]; 30]);
+    for x in 10..21 {
+        for y in 10..21 {
+            if y != 17 || x > 18 {
+                walled.map[x][y] = 1;
+            }
+        }
+    }
+    let (path, distance) = walled.find_flee_path((12f32, 15f32), vec![(12f32, 10f32)], 8f32);
+    let walked: f32 = path.windows(2).map(|s| octile_distance_f32(s[0], s[1])).sum();
+    assert!(distance <= 8f32);
+    assert!((walked - distance).abs() < 0.01);
+
+    // Dead end corridor straight away from the threat
+    for x in 0..30 {
+        for y in 20..30 {
+            if x != 15 {
+                path_find.map[x][y] = 0;
+            }
+        }
+    }
+
+    let (path, _) = path_find.find_flee_path((15f32, 15f32), vec![(15f32, 10f32)], 8f32);
+    let end = *path.last().unwrap();
+    assert!(end.1 < 20);
+    assert!(euclidean_distance((15f32, 10f32), point2_f32(end)) > 8f32);
+}
+
+#[test]
+fn test_find_paths_cooperative() {
+    let mut path_find = PathFind::new_internal(vec![vec![1; 20]; 20]);
+    // Wall with a single cell gap
+    for x in 0..20 {
+        if x != 10 {
+            path_find.map[x][10] = 0;
+        }
+    }
+
+    let starts = vec![(9, 5), (11, 15), (10, 4)];
+    let goals = vec![(11, 15), (9, 5), (10, 16)];
+    let paths = path_find.find_paths_cooperative(starts.clone(), goals.clone(), Some(8), None).unwrap();
+    assert_eq!(paths.len(), 3);
+
+    for (i, path) in paths.iter().enumerate() {
+        assert_eq!(path[0], starts[i]);
+        assert_eq!(*path.last().unwrap(), goals[i]);
+        assert!(path.windows(2).all(|s| octile_distance(s[0], s[1]) <= 14142));
+        assert!(path.iter().all(|c| path_find.map[c.0][c.1] > 0));
+    }
+
+    assert_no_collisions(&paths);
+
+    // Larger units don't fit through the single cell gap
+    let paths = path_find.find_paths_cooperative(vec![(8, 5)], vec![(8, 15)], None, Some(vec![1.5])).unwrap();
+    assert!(paths[0].iter().all(|c| c.1 < 10));
+    for c in paths[0].iter() {
+        assert!((c.0 - 1..=c.0 + 1).all(|x| (c.1 - 1..=c.1 + 1).all(|y| path_find.map[x][y] > 0)));
+    }
+
+    // Larger units keep more space between each other
+    for x in 9..12 {
+        path_find.map[x][10] = 1;
+    }
+    let paths = path_find.find_paths_cooperative(vec![(8, 5), (12, 5)], vec![(8, 15), (12, 15)], None, Some(vec![1.0, 1.0]))
+                         .unwrap();
+    let at = |path: &Vec<(usize, usize)>, t: usize| path[usize::min(t, path.len() - 1)];
+    for t in 0..paths.iter().map(|p| p.len()).max().unwrap() {
+        let a = at(&paths[0], t);
+        let b = at(&paths[1], t);
+        assert!(octile_distance(a, b) > 20000);
+    }
+    assert_eq!(*paths[0].last().unwrap(), (8, 15));
+    assert_eq!(*paths[1].last().unwrap(), (12, 15));
+
+    // Large units at the map edge
+    let paths = path_find.find_paths_cooperative(vec![(0, 0), (19, 19)], vec![(0, 19), (19, 0)], None, Some(vec![1.5, 1.5]))
+                         .unwrap();
+    assert_no_collisions(&paths);
+
+    // The second unit stands in the way of the first in a corridor
+    let mut corridor = vec![vec![0; 3]; 10];
+    for column in corridor.iter_mut().skip(1).take(8) {
+        column[1] = 1;
+    }
+    let path_find = PathFind::new_internal(corridor);
+    let paths = path_find.find_paths_cooperative(vec![(6, 1), (2, 1)], vec![(1, 1), (2, 1)], Some(8), None)
+                         .unwrap();
+    assert_no_collisions(&paths);
+    assert_eq!(paths[1], vec![(2, 1)]);
+    assert_eq!(*paths[0].last().unwrap(), (3, 1));
+
+    assert!(path_find.find_paths_cooperative(vec![(6, 1), (2, 1)], vec![(1, 1)], None, None).is_err());
+}
+
+fn assert_no_collisions(paths: &[Vec<(usize, usize)>]) {
+    let duration = paths.iter().map(|p| p.len()).max().unwrap();
+    let at = |path: &Vec<(usize, usize)>, t: usize| path[usize::min(t, path.len() - 1)];
+
+    for t in 0..duration {
+        for i in 0..paths.len() {
+            for j in i + 1..paths.len() {
+                assert_ne!(at(&paths[i], t), at(&paths[j], t));
+                if t > 0 {
+                    let swapped = at(&paths[i], t) == at(&paths[j], t - 1) && at(&paths[j], t) == at(&paths[i], t - 1);
+                    assert!(!swapped);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_components() {
+    let mut path_find = PathFind::new_internal(vec![vec![1; 30]; 30]);
+    assert!(path_find.is_reachable((2f32, 2f32), (27f32, 27f32)));
+
+    // Wall across the map splits it in two
+    path_find.create_block((15f32, 15f32), (30, 2));
+    assert!(!path_find.is_reachable((2f32, 2f32), (27f32, 27f32)));
+    assert!(!path_find.is_reachable((2f32, 2f32), (15f32, 15f32)));
+    assert!(path_find.is_reachable((2f32, 2f32), (27f32, 2f32)));
+    let (path, distance) = path_find.find_path((2, 2), (27, 27), false, false, None, None, None);
+    assert!(path.is_empty());
+    assert_eq!(distance, 0f32);
+
+    // Opening a gap joins them again
+    path_find.remove_block((20f32, 15f32), (2, 2));
+    assert!(path_find.is_reachable((2f32, 2f32), (27f32, 27f32)));
+    let (path, _) = path_find.find_path((2, 2), (27, 27), false, false, None, None, None);
+    assert!(!path.is_empty());
+
+    // Incremental labels match labels calculated from scratch
+    path_find.create_blocks(vec![(20f32, 15f32), (5f32, 5f32), (8f32, 25f32)], (4, 4));
+    path_find.create_block((25f32, 25f32), (1, 10));
+    let fresh = PathFind::new_internal(path_find.map.clone());
+    let cells: Vec<(f32, f32)> = (0..30).flat_map(|x| (0..30).map(move |y| (x as f32, y as f32))).collect();
+    for a in cells.iter().step_by(7) {
+        for b in cells.iter().step_by(11) {
+            assert_eq!(path_find.is_reachable(*a, *b), fresh.is_reachable(*a, *b));
+        }
+    }
+
+    path_find.reset_void();
+    assert!(path_find.is_reachable((2f32, 2f32), (27f32, 27f32)));
+
+    // Many blocks between queries are labeled together
+    let centers: Vec<(f32, f32)> = (0..6).map(|i| (2.5 + 5.0 * i as f32, 10f32)).collect();
+    path_find.create_blocks(centers, (5, 2));
+    assert!(!path_find.is_reachable((2f32, 2f32), (27f32, 27f32)));
+    assert!(path_find.is_reachable((2f32, 2f32), (27f32, 2f32)));
+    path_find.remove_block((12.5, 10f32), (5, 2));
+    assert!(path_find.is_reachable((2f32, 2f32), (27f32, 27f32)));
+}
+
+#[test]
+fn test_influence_gradient() {
+    let mut path_find = PathFind::new_internal(vec![vec![1; 30]; 30]);
+    for x in 0..4 {
+        path_find.map[x][15] = 0;
+    }
+    assert_eq!(path_find.influence_gradient((3f32, 16f32), 3f32), (0f32, 0f32));
+
+    path_find.add_influence(vec![(10, 15)], 100f32, 8f32);
+    let (x, y) = path_find.influence_gradient((13f32, 15f32), 3f32);
+    assert!(x > 0.99);
+    assert!(y.abs() < 0.01);
+
+    let (x, y) = path_find.influence_gradient((10f32, 12f32), 3f32);
+    assert!(x.abs() < 0.01);
+    assert!(y < -0.99);
+}
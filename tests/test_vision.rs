@@ -0,0 +1,180 @@
+use sc2pathlib::mapping::map_point::MapPoint;
+use sc2pathlib::mapping::vision::{VisionMap, VisionUnit};
+
+const SIZE: usize = 24;
+
+/// Small deterministic generator so the property tests don't need extra dependencies.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        self.0 >> 33
+    }
+}
+
+fn random_points(seed: u64, wall_percent: u64) -> Vec<Vec<MapPoint>> {
+    let mut rng = Lcg(seed);
+    let mut points = vec![vec![MapPoint::new(); SIZE]; SIZE];
+
+    for column in points.iter_mut() {
+        for point in column.iter_mut() {
+            point.height = if rng.next() % 100 < wall_percent {
+                32
+            } else {
+                16
+            };
+        }
+    }
+
+    points
+}
+
+fn seen_from(points: &[Vec<MapPoint>], position: (usize, usize), sight_range: f32) -> Vec<Vec<usize>> {
+    let mut vision = VisionMap::new_internal(SIZE, SIZE);
+    let position = (position.0 as f32, position.1 as f32);
+    vision.add_unit(VisionUnit::new(false, false, position, sight_range, None));
+    vision.calculate_vision_map(points);
+    vision.draw_vision()
+}
+
+#[test]
+fn test_vision_is_symmetric() {
+    let sight_range = 9f32;
+
+    for seed in 1..4 {
+        let points = random_points(seed, 20);
+        let seen: Vec<Vec<Vec<Vec<usize>>>> =
+            (0..SIZE).map(|x| (0..SIZE).map(|y| seen_from(&points, (x, y), sight_range)).collect()).collect();
+
+        let floor: Vec<(usize, usize)> =
+            (0..SIZE).flat_map(|x| (0..SIZE).map(move |y| (x, y))).filter(|p| points[p.0][p.1].height == 16).collect();
+
+        for (x0, y0) in floor.iter() {
+            for (x1, y1) in floor.iter() {
+                assert_eq!(seen[*x0][*y0][*x1][*y1], seen[*x1][*y1][*x0][*y0],
+                           "asymmetric vision between ({}, {}) and ({}, {})",
+                           x0, y0, x1, y1);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_vision_has_no_gaps() {
+    let points = random_points(0, 0);
+
+    for sight_range in [3f32, 7.5f32, 10f32, 11f32] {
+        let center = (SIZE / 2, SIZE / 2);
+        let seen = seen_from(&points, center, sight_range);
+
+        for (x, column) in seen.iter().enumerate() {
+            for (y, status) in column.iter().enumerate() {
+                let dx = x as f32 - center.0 as f32;
+                let dy = y as f32 - center.1 as f32;
+                let expected = (dx * dx + dy * dy <= sight_range * sight_range) as usize;
+                assert_eq!(*status, expected, "({}, {}) with sight range {}", x, y, sight_range);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_vision_is_blocked_by_high_ground() {
+    let mut points = random_points(0, 0);
+    for column in points.iter_mut().skip(14) {
+        for point in column.iter_mut() {
+            point.height = 32;
+        }
+    }
+
+    // Low ground doesn't see high ground or anything behind it
+    let seen = seen_from(&points, (10, 12), 8f32);
+    assert_eq!(seen[13][12], 1);
+    assert_eq!(seen[14][12], 0);
+    assert_eq!(seen[16][12], 0);
+
+    // High ground sees low ground
+    let seen = seen_from(&points, (16, 12), 8f32);
+    assert_eq!(seen[13][12], 1);
+    assert_eq!(seen[10][12], 1);
+}
+
+#[test]
+fn test_detection_range_is_separate() {
+    let points = random_points(0, 0);
+    let mut vision = VisionMap::new_internal(SIZE, SIZE);
+    vision.add_unit(VisionUnit::new(true, true, (12f32, 12f32), 4f32, Some(8f32)));
+    vision.calculate_vision_map(&points);
+
+    assert_eq!(vision.vision_status((12f32, 14f32)), 2);
+    assert_eq!(vision.vision_status((12f32, 18f32)), 3);
+    assert_eq!(vision.vision_status((12f32, 21f32)), 0);
+}
+
+#[test]
+fn test_ground_detection_behind_high_ground() {
+    let mut points = random_points(0, 0);
+    for column in points.iter_mut().skip(14) {
+        for point in column.iter_mut() {
+            point.height = 32;
+        }
+    }
+
+    let mut vision = VisionMap::new_internal(SIZE, SIZE);
+    vision.add_unit(VisionUnit::new(true, false, (10f32, 12f32), 8f32, Some(7f32)));
+    vision.add_unit(VisionUnit::new(false, false, (4f32, 4f32), 3f32, None));
+    vision.calculate_vision_map(&points);
+
+    assert_eq!(vision.vision_status((13f32, 12f32)), 2);
+    assert_eq!(vision.vision_status((15f32, 12f32)), 3);
+    assert_eq!(vision.vision_status((4f32, 5f32)), 1);
+}
+
+#[test]
+fn test_incremental_vision_matches_full_recompute() {
+    let points = random_points(7, 15);
+    let units = [VisionUnit::new(false, false, (5f32, 5f32), 6f32, None),
+                 VisionUnit::new(true, true, (12f32, 8f32), 5f32, Some(7f32)),
+                 VisionUnit::new(false, false, (18f32, 17f32), 8f32, None)];
+
+    let mut incremental = VisionMap::new_internal(SIZE, SIZE);
+    for (id, unit) in units.iter().enumerate() {
+        incremental.set_unit(id as u64, unit.clone(), &points);
+    }
+    // Overlapping vision stays when one of the units leaves
+    assert!(incremental.move_unit(0, (10f32, 10f32), &points));
+    assert!(incremental.move_unit(0, (5f32, 5f32), &points));
+    assert!(incremental.remove_unit(2));
+    assert!(!incremental.remove_unit(2));
+
+    let mut full = VisionMap::new_internal(SIZE, SIZE);
+    full.add_unit(units[0].clone());
+    full.add_unit(units[1].clone());
+    full.calculate_vision_map(&points);
+
+    assert_eq!(incremental.draw_vision(), full.draw_vision());
+}
+
+#[test]
+fn test_los_blockers() {
+    let mut points = random_points(0, 0);
+    for column in points.iter_mut().skip(12).take(3) {
+        for point in column.iter_mut().skip(10).take(5) {
+            point.los_blocker = true;
+        }
+    }
+
+    // Blockers can't be seen into or through from outside
+    let seen = seen_from(&points, (8, 12), 9f32);
+    assert_eq!(seen[11][12], 1);
+    assert_eq!(seen[12][12], 0);
+    assert_eq!(seen[16][12], 0);
+    assert_eq!(seen[14][7], 1);
+
+    // Units inside blockers see out
+    let seen = seen_from(&points, (13, 12), 9f32);
+    assert_eq!(seen[12][12], 1);
+    assert_eq!(seen[8][12], 1);
+    assert_eq!(seen[18][12], 1);
+}